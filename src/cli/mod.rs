@@ -0,0 +1,1371 @@
+use chrono::{Duration, NaiveDate};
+use clap::{Parser, Subcommand};
+use error_stack::{IntoReport, Result, ResultExt};
+
+use crate::{
+    config::ConfigLayer,
+    db::{JiraDatabase, JiraDatabaseError, Sections},
+    models::{
+        Epic, EpicUpdate, LabelUpdate, Link, LinkKind, Priority, SortKey, Status, Story,
+        StoryUpdate, User,
+    },
+};
+
+mod output;
+pub use output::Format;
+use output::*;
+
+#[derive(Debug)]
+pub enum CliError {
+    Create,
+    Read,
+    Update,
+    Delete,
+    Migrate,
+    Format,
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let m = match self {
+            CliError::Create => "Failed to create item.",
+            CliError::Read => "Failed to read item.",
+            CliError::Update => "Failed to update item.",
+            CliError::Delete => "Failed to delete item.",
+            CliError::Migrate => "Failed to migrate database.",
+            CliError::Format => "Failed to format output.",
+        };
+        write!(f, "{}", m)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Runs the interactive UI when no subcommand is given.
+#[derive(Debug, Parser)]
+#[command(about = "A JIRA-like issue tracker.")]
+pub struct Cli {
+    /// Output format for created ids, lists and single items.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+    /// Database file, overriding config files and `JIRA_DB_PATH`.
+    #[arg(long, global = true)]
+    pub db: Option<String>,
+    /// Storage backend (json or sqlite), overriding config files and `JIRA_BACKEND`.
+    #[arg(long, global = true)]
+    pub backend: Option<String>,
+    /// Current username, overriding config files and `JIRA_USER`.
+    #[arg(long, global = true)]
+    pub user: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage epics.
+    #[command(subcommand)]
+    Epic(EpicCommand),
+    /// Manage stories.
+    #[command(subcommand)]
+    Story(StoryCommand),
+    /// Manage the user directory.
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Discuss epics and stories.
+    #[command(subcommand)]
+    Comment(CommentCommand),
+    /// Restore or purge deleted epics and stories.
+    #[command(subcommand)]
+    Trash(TrashCommand),
+    /// Show the change history, newest first.
+    Log {
+        /// Only changes to this epic or story.
+        item_id: Option<u32>,
+        /// Show at most this many changes.
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Revert your latest change.
+    Undo,
+    /// Repeat your latest undone change.
+    Redo,
+    /// Create a new, empty database.
+    Init {
+        /// Seed the database with a sample project.
+        #[arg(long)]
+        sample: bool,
+    },
+    /// Upgrade the database to the current schema version.
+    Migrate {
+        /// Only list the upgrade steps that would be applied.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EpicCommand {
+    /// Create an epic and print its id.
+    Create {
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// blocker, critical, major, minor or trivial.
+        #[arg(long, default_value_t = Priority::default())]
+        priority: Priority,
+        /// Due date as YYYY-MM-DD.
+        #[arg(long)]
+        due: Option<NaiveDate>,
+    },
+    /// List all epics.
+    List {
+        /// Only epics with this priority.
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// Only epics with all of these labels.
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// id or priority.
+        #[arg(long, default_value_t = SortKey::default())]
+        sort: SortKey,
+    },
+    /// Show an epic and its stories.
+    Show { id: u32 },
+    /// Change the status of an epic to one its workflow allows next.
+    Status {
+        id: u32,
+        status: Status,
+        /// Move the stories that are not done along when finishing the epic.
+        #[arg(long)]
+        cascade: bool,
+    },
+    /// Change the name, description, priority or due date of an epic.
+    Edit {
+        id: u32,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// Due date as YYYY-MM-DD.
+        #[arg(long)]
+        due: Option<NaiveDate>,
+        /// Remove the due date.
+        #[arg(long, conflicts_with = "due")]
+        clear_due: bool,
+    },
+    /// Assign an epic to a username, or unassign it when none is given.
+    Assign { id: u32, username: Option<String> },
+    /// Add or remove labels of an epic.
+    Label {
+        id: u32,
+        #[arg(long)]
+        add: Vec<String>,
+        #[arg(long)]
+        remove: Vec<String>,
+    },
+    /// Delete an epic and all of its stories.
+    Delete { id: u32 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StoryCommand {
+    /// Create a story in an epic and print its id.
+    Create {
+        #[arg(long)]
+        epic: u32,
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// blocker, critical, major, minor or trivial.
+        #[arg(long, default_value_t = Priority::default())]
+        priority: Priority,
+        /// Estimate in story points.
+        #[arg(long)]
+        points: Option<u32>,
+        /// Due date as YYYY-MM-DD.
+        #[arg(long)]
+        due: Option<NaiveDate>,
+    },
+    /// List stories, optionally only those of one epic, assignee or priority.
+    List {
+        #[arg(long)]
+        epic: Option<u32>,
+        /// Only stories with this priority.
+        #[arg(long)]
+        priority: Option<Priority>,
+        /// Only stories with all of these labels.
+        #[arg(long = "label")]
+        labels: Vec<String>,
+        /// id or priority.
+        #[arg(long, default_value_t = SortKey::default())]
+        sort: SortKey,
+        /// Only stories assigned to this username.
+        #[arg(long)]
+        assignee: Option<String>,
+        /// Only stories assigned to the current user.
+        #[arg(long, conflicts_with = "assignee")]
+        mine: bool,
+    },
+    /// Show a story.
+    Show { id: u32 },
+    /// Change the status of a story to one its workflow allows next.
+    Status { id: u32, status: Status },
+    /// Change the name, description, priority, points or due date of a story.
+    Edit {
+        id: u32,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long)]
+        priority: Option<Priority>,
+        #[arg(long)]
+        points: Option<u32>,
+        /// Remove the estimate.
+        #[arg(long, conflicts_with = "points")]
+        clear_points: bool,
+        /// Due date as YYYY-MM-DD.
+        #[arg(long)]
+        due: Option<NaiveDate>,
+        /// Remove the due date.
+        #[arg(long, conflicts_with = "due")]
+        clear_due: bool,
+    },
+    /// Assign a story to a username, or unassign it when none is given.
+    Assign { id: u32, username: Option<String> },
+    /// Add or remove labels of a story.
+    Label {
+        id: u32,
+        #[arg(long)]
+        add: Vec<String>,
+        #[arg(long)]
+        remove: Vec<String>,
+    },
+    /// Link a story to another one and print the link id, e.g. `3 blocks 5`.
+    Link {
+        id: u32,
+        /// blocks, relates or duplicates.
+        kind: LinkKind,
+        other: u32,
+    },
+    /// Remove a link between stories.
+    Unlink { link_id: u32 },
+    /// Delete a story.
+    Delete { id: u32 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Add a user and print its id.
+    Add {
+        username: String,
+        /// Full name, defaulting to the username.
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// List all users.
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CommentCommand {
+    /// Comment on an epic or story and print the comment id.
+    Add { item_id: u32, body: String },
+    /// List the comments on an epic or story, oldest first.
+    List { item_id: u32 },
+    /// Replace the body of a comment.
+    Edit { id: u32, body: String },
+    /// Delete a comment.
+    Delete { id: u32 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TrashCommand {
+    /// List deleted epics and stories, most recently deleted first.
+    List,
+    /// Put a deleted epic, with its stories, or story back.
+    Restore { id: u32 },
+    /// Permanently delete items that have been in the trash for a while.
+    Purge {
+        /// Keep items deleted in the last this many days.
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+    },
+}
+
+impl Cli {
+    pub fn config_overrides(&self) -> ConfigLayer {
+        let mut layer = ConfigLayer::default();
+        layer.database.path = self.db.clone();
+        layer.database.backend = self.backend.clone();
+        layer.user.username = self.user.clone();
+        layer
+    }
+}
+
+/// `user` is the current username, recorded as reporter of created items.
+/// Runs `command`, whose writes are undone together. An existing database
+/// in an older schema is backed up and upgraded first, as in the
+/// interactive UI.
+pub fn run(
+    command: Command,
+    format: Format,
+    db: &JiraDatabase,
+    user: Option<&str>,
+) -> Result<(), CliError> {
+    if !matches!(command, Command::Init { .. } | Command::Migrate { .. }) && db.exists() {
+        for step in db.migrate(false).change_context(CliError::Migrate)? {
+            eprintln!("Applied {}", step);
+        }
+    }
+    db.in_action(|| run_command(command, format, db, user))
+}
+
+fn run_command(
+    command: Command,
+    format: Format,
+    db: &JiraDatabase,
+    user: Option<&str>,
+) -> Result<(), CliError> {
+    match command {
+        Command::Epic(command) => run_epic(command, format, db, user),
+        Command::Story(command) => run_story(command, format, db, user),
+        Command::User(command) => run_user(command, format, db),
+        Command::Comment(command) => run_comment(command, format, db, user),
+        Command::Trash(command) => run_trash(command, format, db),
+        Command::Log { item_id, limit } => {
+            let db_state = db
+                .read_db_with(Sections::HISTORY)
+                .change_context(CliError::Read)?;
+            let events = db_state
+                .history
+                .iter()
+                .rev()
+                .filter(|event| item_id.is_none_or(|item_id| event.item_id == item_id))
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|event| EventView { event })
+                .collect::<Vec<_>>();
+            print!("{}", render_list(format, &events)?);
+            Ok(())
+        }
+        Command::Undo => {
+            let entry = db.undo().change_context(CliError::Update)?;
+            print!("{}", render_one(format, &JournalView::new("undo", &entry))?);
+            Ok(())
+        }
+        Command::Redo => {
+            let entry = db.redo().change_context(CliError::Update)?;
+            print!("{}", render_one(format, &JournalView::new("redo", &entry))?);
+            Ok(())
+        }
+        Command::Init { sample } => {
+            db.init(sample).change_context(CliError::Create)?;
+            println!("Created a new Jira database.");
+            Ok(())
+        }
+        Command::Migrate { dry_run } => {
+            let steps = db.migrate(dry_run).change_context(CliError::Migrate)?;
+            for step in steps {
+                println!(
+                    "{} {}",
+                    if dry_run { "Would apply" } else { "Applied" },
+                    step
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run_epic(
+    command: EpicCommand,
+    format: Format,
+    db: &JiraDatabase,
+    user: Option<&str>,
+) -> Result<(), CliError> {
+    match command {
+        EpicCommand::Create {
+            name,
+            description,
+            priority,
+            due,
+        } => {
+            let mut epic = Epic::new(name, description);
+            epic.priority = priority;
+            epic.due_date = due;
+            epic.reporter = reporter_id(db, user)?;
+            let id = db.create_epic(epic).change_context(CliError::Create)?;
+            print!("{}", render_one(format, &CreatedView { id })?);
+        }
+        EpicCommand::List {
+            priority,
+            labels,
+            sort,
+        } => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let epics = db_state
+                .sorted_epic_ids(sort)
+                .into_iter()
+                .map(|id| EpicView::new(id, &db_state.epics[&id], &db_state, &db.workflows.story))
+                .filter(|view| priority.is_none_or(|priority| view.priority == priority))
+                .filter(|view| labels.iter().all(|label| view.labels.contains(label)))
+                .collect::<Vec<_>>();
+            print!("{}", render_list(format, &epics)?);
+        }
+        EpicCommand::Show { id } => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let epic = db_state
+                .epics
+                .get(&id)
+                .ok_or(JiraDatabaseError::NoEpicWithID)
+                .into_report()
+                .change_context(CliError::Read)?;
+            print!(
+                "{}",
+                render_one(
+                    format,
+                    &EpicView::new(id, epic, &db_state, &db.workflows.story)
+                )?
+            );
+        }
+        EpicCommand::Status {
+            id,
+            status,
+            cascade,
+        } => if cascade {
+            db.update_epic_status_cascading(id, status)
+        } else {
+            db.update_epic_status(id, status)
+        }
+        .change_context(CliError::Update)?,
+        EpicCommand::Edit {
+            id,
+            name,
+            description,
+            priority,
+            due,
+            clear_due,
+        } => db
+            .update_epic(
+                id,
+                EpicUpdate {
+                    name,
+                    description,
+                    priority,
+                    due_date: clearable_update(due, clear_due),
+                },
+            )
+            .change_context(CliError::Update)?,
+        EpicCommand::Assign { id, username } => {
+            let assignee = user_id(db, username.as_deref())?;
+            db.assign_epic(id, assignee)
+                .change_context(CliError::Update)?
+        }
+        EpicCommand::Label { id, add, remove } => db
+            .update_epic_labels(id, LabelUpdate { add, remove })
+            .change_context(CliError::Update)?,
+        EpicCommand::Delete { id } => db.delete_epic(id).change_context(CliError::Delete)?,
+    }
+
+    Ok(())
+}
+
+fn run_story(
+    command: StoryCommand,
+    format: Format,
+    db: &JiraDatabase,
+    user: Option<&str>,
+) -> Result<(), CliError> {
+    match command {
+        StoryCommand::Create {
+            epic,
+            name,
+            description,
+            priority,
+            points,
+            due,
+        } => {
+            let mut story = Story::new(name, description);
+            story.priority = priority;
+            story.points = points;
+            story.due_date = due;
+            story.reporter = reporter_id(db, user)?;
+            let id = db
+                .create_story(story, epic)
+                .change_context(CliError::Create)?;
+            print!("{}", render_one(format, &CreatedView { id })?);
+        }
+        StoryCommand::List {
+            epic,
+            priority,
+            labels,
+            sort,
+            assignee,
+            mine,
+        } => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let assignee = if mine {
+                Some(
+                    user.ok_or(CliError::Read)
+                        .into_report()
+                        .attach_printable("No current user, pass `--user` or set `JIRA_USER`.")?,
+                )
+            } else {
+                assignee.as_deref()
+            };
+            // An unknown username matches no story rather than failing.
+            let assignee = assignee.map(|username| db_state.find_user(username));
+            let ids = match epic {
+                Some(epic_id) => {
+                    let epic = db_state
+                        .epics
+                        .get(&epic_id)
+                        .ok_or(JiraDatabaseError::NoEpicWithID)
+                        .into_report()
+                        .change_context(CliError::Read)?;
+                    db_state.sorted_story_ids(&epic.stories, sort)
+                }
+                None => db_state.sorted_story_ids(db_state.stories.keys(), sort),
+            };
+            let stories = ids
+                .into_iter()
+                .map(|id| StoryView {
+                    id,
+                    story: &db_state.stories[&id],
+                })
+                .filter(|view| {
+                    assignee.is_none_or(|assignee| {
+                        assignee.is_some() && view.story.assignee == assignee
+                    })
+                })
+                .filter(|view| priority.is_none_or(|priority| view.story.priority == priority))
+                .filter(|view| labels.iter().all(|label| view.story.labels.contains(label)))
+                .collect::<Vec<_>>();
+            print!("{}", render_list(format, &stories)?);
+        }
+        StoryCommand::Show { id } => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let story = db_state
+                .stories
+                .get(&id)
+                .ok_or(JiraDatabaseError::NoStoryWithID)
+                .into_report()
+                .change_context(CliError::Read)?;
+            print!("{}", render_one(format, &StoryView { id, story })?);
+        }
+        StoryCommand::Status { id, status } => db
+            .update_story_status(id, status)
+            .change_context(CliError::Update)?,
+        StoryCommand::Edit {
+            id,
+            name,
+            description,
+            priority,
+            points,
+            clear_points,
+            due,
+            clear_due,
+        } => db
+            .update_story(
+                id,
+                StoryUpdate {
+                    name,
+                    description,
+                    priority,
+                    points: clearable_update(points, clear_points),
+                    due_date: clearable_update(due, clear_due),
+                },
+            )
+            .change_context(CliError::Update)?,
+        StoryCommand::Assign { id, username } => {
+            let assignee = user_id(db, username.as_deref())?;
+            db.assign_story(id, assignee)
+                .change_context(CliError::Update)?
+        }
+        StoryCommand::Label { id, add, remove } => db
+            .update_story_labels(id, LabelUpdate { add, remove })
+            .change_context(CliError::Update)?,
+        StoryCommand::Link { id, kind, other } => {
+            let id = db
+                .add_link(Link::new(id, kind, other))
+                .change_context(CliError::Create)?;
+            print!("{}", render_one(format, &CreatedView { id })?);
+        }
+        StoryCommand::Unlink { link_id } => {
+            db.remove_link(link_id).change_context(CliError::Delete)?
+        }
+        StoryCommand::Delete { id } => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let epic_id = db_state
+                .epics
+                .iter()
+                .find(|(_, epic)| epic.stories.contains(&id))
+                .map(|(epic_id, _)| *epic_id)
+                .ok_or(JiraDatabaseError::NoStoryWithID)
+                .into_report()
+                .change_context(CliError::Delete)?;
+            db.delete_story(epic_id, id)
+                .change_context(CliError::Delete)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run_user(command: UserCommand, format: Format, db: &JiraDatabase) -> Result<(), CliError> {
+    match command {
+        UserCommand::Add { username, name } => {
+            let name = name.unwrap_or_else(|| username.clone());
+            let id = db
+                .create_user(User::new(username, name))
+                .change_context(CliError::Create)?;
+            print!("{}", render_one(format, &CreatedView { id })?);
+        }
+        UserCommand::List => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let users = itertools::sorted(db_state.users.keys())
+                .map(|id| UserView {
+                    id: *id,
+                    user: &db_state.users[id],
+                })
+                .collect::<Vec<_>>();
+            print!("{}", render_list(format, &users)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// `Some(None)` when `clear` is set, so the update removes the value.
+fn clearable_update<T>(value: Option<T>, clear: bool) -> Option<Option<T>> {
+    if clear {
+        Some(None)
+    } else {
+        value.map(Some)
+    }
+}
+
+fn run_comment(
+    command: CommentCommand,
+    format: Format,
+    db: &JiraDatabase,
+    user: Option<&str>,
+) -> Result<(), CliError> {
+    match command {
+        CommentCommand::Add { item_id, body } => {
+            let author = reporter_id(db, user)?;
+            let id = db
+                .add_comment(item_id, author, body)
+                .change_context(CliError::Create)?;
+            print!("{}", render_one(format, &CreatedView { id })?);
+        }
+        CommentCommand::List { item_id } => {
+            let db_state = db.read_db().change_context(CliError::Read)?;
+            let comments = db_state
+                .comments_on(item_id)
+                .into_iter()
+                .map(|(id, comment)| CommentView {
+                    id,
+                    author_name: db_state.username(comment.author),
+                    comment,
+                })
+                .collect::<Vec<_>>();
+            print!("{}", render_list(format, &comments)?);
+        }
+        CommentCommand::Edit { id, body } => {
+            db.edit_comment(id, body).change_context(CliError::Update)?
+        }
+        CommentCommand::Delete { id } => db.delete_comment(id).change_context(CliError::Delete)?,
+    }
+
+    Ok(())
+}
+
+fn run_trash(command: TrashCommand, format: Format, db: &JiraDatabase) -> Result<(), CliError> {
+    match command {
+        TrashCommand::List => {
+            let db_state = db
+                .read_db_with(Sections::TRASH)
+                .change_context(CliError::Read)?;
+            let entries = db_state
+                .trashed()
+                .into_iter()
+                .map(|(id, entry)| TrashView { id, entry })
+                .collect::<Vec<_>>();
+            print!("{}", render_list(format, &entries)?);
+        }
+        TrashCommand::Restore { id } => db.restore(id).change_context(CliError::Update)?,
+        TrashCommand::Purge { days } => {
+            let purged = db
+                .purge_trash(Duration::days(days.into()))
+                .change_context(CliError::Delete)?;
+            println!("Purged {} items from the trash.", purged);
+        }
+    }
+
+    Ok(())
+}
+
+/// Id of the current user, registering it in the user directory first.
+fn reporter_id(db: &JiraDatabase, user: Option<&str>) -> Result<Option<u32>, CliError> {
+    user.map(|username| db.ensure_user(username))
+        .transpose()
+        .change_context(CliError::Create)
+}
+
+/// Looks up an existing user by name; `None` stays `None` to unassign.
+fn user_id(db: &JiraDatabase, username: Option<&str>) -> Result<Option<u32>, CliError> {
+    let Some(username) = username else {
+        return Ok(None);
+    };
+    let db_state = db.read_db().change_context(CliError::Read)?;
+    db_state
+        .find_user(username)
+        .ok_or(JiraDatabaseError::NoUserWithID)
+        .into_report()
+        .attach_printable_lazy(|| username.to_owned())
+        .change_context(CliError::Update)
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::test_utils::MockDB,
+        models::{CloseEpicRule, Invariants},
+    };
+
+    fn parse(args: &[&str]) -> Command {
+        Cli::try_parse_from(std::iter::once("bootcamp_jira").chain(args.iter().copied()))
+            .unwrap()
+            .command
+            .unwrap()
+    }
+
+    #[test]
+    fn cli_should_parse_subcommands() {
+        assert!(Cli::try_parse_from(["bootcamp_jira"])
+            .unwrap()
+            .command
+            .is_none());
+        assert!(matches!(
+            parse(&["story", "status", "3", "resolved"]),
+            Command::Story(StoryCommand::Status {
+                id: 3,
+                status: Status::Resolved
+            })
+        ));
+        assert!(matches!(
+            parse(&["story", "create", "--epic", "1", "--name", "n"]),
+            Command::Story(StoryCommand::Create { epic: 1, .. })
+        ));
+        // Unknown states are left to the workflow.
+        assert!(matches!(
+            parse(&["story", "status", "3", "Done"]),
+            Command::Story(StoryCommand::Status {
+                status: Status::Custom(ref name),
+                ..
+            }) if name == "done"
+        ));
+
+        let cli = Cli::try_parse_from(["bootcamp_jira", "epic", "list", "--format", "ndjson"]);
+        assert_eq!(cli.unwrap().format, Format::Ndjson);
+    }
+
+    #[test]
+    fn run_should_create_update_and_delete_items() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "create", "--epic", "1", "--name", "story"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "status", "2", "in-progress"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["epic", "status", "1", "closed"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "edit", "2", "--description", "details"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&1].name, "epic");
+        assert_eq!(db_state.epics[&1].status, Status::Closed);
+        assert_eq!(db_state.stories[&2].status, Status::InProgress);
+        assert_eq!(db_state.stories[&2].name, "story");
+        assert_eq!(db_state.stories[&2].description, "details");
+
+        run(parse(&["story", "delete", "2"]), Format::Table, &db, None).unwrap();
+        assert!(db.read_db().unwrap().stories.is_empty());
+
+        run(parse(&["epic", "delete", "1"]), Format::Table, &db, None).unwrap();
+        assert!(db.read_db().unwrap().epics.is_empty());
+    }
+
+    #[test]
+    fn run_should_manage_users_and_assignees() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(parse(&["user", "add", "asmith"]), Format::Table, &db, None).unwrap();
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            Some("jdoe"),
+        )
+        .unwrap();
+        run(
+            parse(&["story", "create", "--epic", "1", "--name", "story"]),
+            Format::Table,
+            &db,
+            Some("jdoe"),
+        )
+        .unwrap();
+        run(
+            parse(&["story", "assign", "2", "asmith"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert!(run(
+            parse(&["story", "assign", "2", "nobody"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+
+        let db_state = db.read_db().unwrap();
+        let asmith = db_state.find_user("asmith");
+        let jdoe = db_state.find_user("jdoe");
+        assert!(jdoe.is_some());
+        assert_eq!(db_state.epics[&1].reporter, jdoe);
+        assert_eq!(db_state.stories[&2].reporter, jdoe);
+        assert_eq!(db_state.stories[&2].assignee, asmith);
+
+        run(parse(&["story", "assign", "2"]), Format::Table, &db, None).unwrap();
+        assert_eq!(db.read_db().unwrap().stories[&2].assignee, None);
+        assert!(run(parse(&["user", "add", "asmith"]), Format::Table, &db, None).is_err());
+    }
+
+    #[test]
+    fn run_should_set_and_sort_by_priority() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        for (name, priority) in [("a", "minor"), ("b", "blocker"), ("c", "minor")] {
+            run(
+                parse(&["epic", "create", "--name", name, "--priority", priority]),
+                Format::Table,
+                &db,
+                None,
+            )
+            .unwrap();
+        }
+        run(
+            parse(&["epic", "edit", "3", "--priority", "critical"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&3].priority, Priority::Critical);
+        assert_eq!(db_state.sorted_epic_ids(SortKey::Priority), vec![2, 3, 1]);
+        assert!(matches!(
+            parse(&["story", "list", "--sort", "priority", "--priority", "major"]),
+            Command::Story(StoryCommand::List {
+                sort: SortKey::Priority,
+                priority: Some(Priority::Major),
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["bootcamp_jira", "epic", "list", "--sort", "name"]).is_err());
+    }
+
+    #[test]
+    fn run_should_add_and_remove_labels() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["epic", "label", "1", "--add", "backend", "--add", "ui"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["epic", "label", "1", "--remove", "ui"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert!(run(
+            parse(&["epic", "label", "1", "--add", "a,b"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics[&1].labels.iter().eq(["backend"].iter()));
+        assert!(matches!(
+            parse(&["story", "list", "--label", "backend", "--label", "ui"]),
+            Command::Story(StoryCommand::List { labels, .. }) if labels == ["backend", "ui"]
+        ));
+    }
+
+    #[test]
+    fn run_should_set_and_clear_due_dates() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic", "--due", "2024-02-29"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "create", "--epic", "1", "--name", "story"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "edit", "2", "--due", "2024-03-01"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["epic", "edit", "1", "--clear-due"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&1].due_date, None);
+        assert_eq!(
+            db_state.stories[&2].due_date,
+            NaiveDate::from_ymd_opt(2024, 3, 1)
+        );
+        assert!(
+            Cli::try_parse_from(["bootcamp_jira", "story", "edit", "2", "--due", "soon"]).is_err()
+        );
+    }
+
+    #[test]
+    fn run_should_set_and_clear_points() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&[
+                "story", "create", "--epic", "1", "--name", "story", "--points", "5",
+            ]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert_eq!(db.read_db().unwrap().stories[&2].points, Some(5));
+
+        run(
+            parse(&["story", "edit", "2", "--clear-points"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert_eq!(db.read_db().unwrap().stories[&2].points, None);
+        assert!(Cli::try_parse_from([
+            "bootcamp_jira",
+            "story",
+            "edit",
+            "2",
+            "--points",
+            "3",
+            "--clear-points"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn run_should_manage_comments() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["comment", "add", "1", "first"]),
+            Format::Table,
+            &db,
+            Some("jdoe"),
+        )
+        .unwrap();
+        run(
+            parse(&["comment", "add", "1", "second"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["comment", "edit", "1", "first, edited"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(parse(&["comment", "delete", "2"]), Format::Table, &db, None).unwrap();
+        run(parse(&["comment", "list", "1"]), Format::Json, &db, None).unwrap();
+        assert!(run(
+            parse(&["comment", "add", "9", "x"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+
+        let db_state = db.read_db().unwrap();
+        let comments = db_state.comments_on(1);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].1.body, "first, edited");
+        assert_eq!(comments[0].1.author, db_state.find_user("jdoe"));
+    }
+
+    #[test]
+    fn run_should_record_history() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()))
+            .with_actor(Some("jdoe".to_owned()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["epic", "status", "1", "closed"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["log", "1", "--limit", "1"]),
+            Format::Json,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let history = db_state.history_of(1);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].field, "status");
+        assert_eq!(history[1].old, "OPEN");
+        assert_eq!(history[1].new, "CLOSED");
+        assert_eq!(history[1].actor.as_deref(), Some("jdoe"));
+    }
+
+    #[test]
+    fn run_should_undo_and_redo() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(parse(&["epic", "delete", "1"]), Format::Table, &db, None).unwrap();
+
+        run(parse(&["undo"]), Format::Table, &db, None).unwrap();
+        assert!(db.read_db().unwrap().epics.contains_key(&1));
+
+        run(parse(&["redo"]), Format::Json, &db, None).unwrap();
+        assert!(db.read_db().unwrap().epics.is_empty());
+        assert!(run(parse(&["redo"]), Format::Table, &db, None).is_err());
+    }
+
+    #[test]
+    fn run_should_undo_a_command_as_a_whole() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            Some("jdoe"),
+        )
+        .unwrap();
+        run(parse(&["undo"]), Format::Table, &db, None).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics.is_empty());
+        assert!(db_state.users.is_empty());
+    }
+
+    #[test]
+    fn run_should_link_stories() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        for name in ["first", "second"] {
+            run(
+                parse(&["story", "create", "--epic", "1", "--name", name]),
+                Format::Table,
+                &db,
+                None,
+            )
+            .unwrap();
+        }
+
+        run(
+            parse(&["story", "link", "2", "blocks", "3"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert!(run(
+            parse(&["story", "link", "3", "blocks", "2"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+        assert!(run(
+            parse(&["story", "status", "3", "resolved"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+
+        run(parse(&["story", "unlink", "1"]), Format::Table, &db, None).unwrap();
+        run(
+            parse(&["story", "status", "3", "resolved"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert!(db.read_db().unwrap().links.is_empty());
+    }
+
+    #[test]
+    fn run_should_restore_and_purge_trash() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "create", "--epic", "1", "--name", "story"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(parse(&["epic", "delete", "1"]), Format::Table, &db, None).unwrap();
+        run(parse(&["trash", "list"]), Format::Json, &db, None).unwrap();
+
+        run(parse(&["trash", "restore", "1"]), Format::Table, &db, None).unwrap();
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&1].stories, vec![2]);
+        assert!(db_state.stories.contains_key(&2));
+
+        run(parse(&["story", "delete", "2"]), Format::Table, &db, None).unwrap();
+        run(parse(&["trash", "purge"]), Format::Table, &db, None).unwrap();
+        assert_eq!(db.read_db().unwrap().trash.len(), 1);
+
+        run(
+            parse(&["trash", "purge", "--days", "0"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        assert!(db.read_db().unwrap().trash.is_empty());
+        assert!(run(parse(&["trash", "restore", "2"]), Format::Table, &db, None).is_err());
+    }
+
+    #[test]
+    fn run_should_back_up_and_migrate_old_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("db.json").to_string_lossy().into_owned();
+        let file_contents = r#"{ "last_item_id": 1, "epics": { "1": { "id": 1, "name": "old", "description": "", "status": "Open", "stories": [] } }, "stories": {} }"#;
+        std::fs::write(&file_path, file_contents).unwrap();
+        let db = JiraDatabase::new(file_path.clone());
+
+        run(parse(&["epic", "list"]), Format::Json, &db, None).unwrap();
+
+        let backup = std::fs::read_to_string(format!("{}.v0.bak", file_path)).unwrap();
+        assert_eq!(backup, file_contents);
+        assert!(db.migrate(true).unwrap().is_empty());
+        assert_eq!(db.read_db().unwrap().epics[&1].name, "old");
+    }
+
+    #[test]
+    fn run_should_reject_purge_days_out_of_range() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        assert!(Cli::try_parse_from(["bootcamp_jira", "trash", "purge", "--days", "-1"]).is_err());
+        assert!(run(
+            parse(&["trash", "purge", "--days", &u32::MAX.to_string()]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn run_should_reject_states_outside_the_workflow() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        assert!(run(
+            parse(&["epic", "status", "1", "done"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+        assert_eq!(db.read_db().unwrap().epics[&1].status, Status::Open);
+    }
+
+    #[test]
+    fn run_should_close_epics_with_their_stories() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new())).with_invariants(Invariants {
+            close_epic: CloseEpicRule::Block,
+            ..Default::default()
+        });
+
+        run(
+            parse(&["epic", "create", "--name", "epic"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+        run(
+            parse(&["story", "create", "--epic", "1", "--name", "story"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        assert!(run(
+            parse(&["epic", "status", "1", "closed"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+        run(
+            parse(&["epic", "status", "1", "closed", "--cascade"]),
+            Format::Table,
+            &db,
+            None,
+        )
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&1].status, Status::Closed);
+        assert_eq!(db_state.stories[&2].status, Status::Closed);
+    }
+
+    #[test]
+    fn run_should_error_for_missing_items() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        assert!(run(parse(&["story", "delete", "9"]), Format::Table, &db, None).is_err());
+        assert!(run(parse(&["epic", "show", "9"]), Format::Table, &db, None).is_err());
+        assert!(run(
+            parse(&["story", "create", "--epic", "9", "--name", "n"]),
+            Format::Table,
+            &db,
+            None
+        )
+        .is_err());
+    }
+}
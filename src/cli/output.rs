@@ -0,0 +1,534 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use error_stack::{IntoReport, Result, ResultExt};
+use serde::Serialize;
+
+use super::CliError;
+use crate::{
+    models::{
+        format_date, format_timestamp, join_labels, AuditEvent, Comment, DBState, Epic,
+        JournalEntry, Priority, Progress, Status, Story, TrashEntry, User, Workflow,
+    },
+    ui::get_column_string,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Format {
+    /// Fixed-width columns, as in the interactive UI.
+    Table,
+    /// A single JSON document.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoryView<'a> {
+    pub id: u32,
+    #[serde(flatten)]
+    pub story: &'a Story,
+}
+
+/// An epic with its `stories` ids resolved into the stories themselves.
+#[derive(Debug, Serialize)]
+pub struct EpicView<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    pub description: &'a str,
+    pub status: &'a Status,
+    pub priority: Priority,
+    pub assignee: Option<u32>,
+    pub reporter: Option<u32>,
+    pub labels: &'a BTreeSet<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub due_date: Option<NaiveDate>,
+    pub points: Progress,
+    pub stories: Vec<StoryView<'a>>,
+}
+
+impl<'a> EpicView<'a> {
+    pub fn new(id: u32, epic: &'a Epic, db_state: &'a DBState, workflow: &Workflow) -> Self {
+        Self {
+            id,
+            name: &epic.name,
+            description: &epic.description,
+            status: &epic.status,
+            priority: epic.priority,
+            assignee: epic.assignee,
+            reporter: epic.reporter,
+            labels: &epic.labels,
+            created_at: epic.created_at,
+            updated_at: epic.updated_at,
+            due_date: epic.due_date,
+            points: db_state.progress(epic, workflow),
+            stories: itertools::sorted(epic.stories.iter())
+                .filter_map(|story_id| {
+                    db_state.stories.get(story_id).map(|story| StoryView {
+                        id: *story_id,
+                        story,
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserView<'a> {
+    pub id: u32,
+    #[serde(flatten)]
+    pub user: &'a User,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentView<'a> {
+    pub id: u32,
+    /// Username of the author, next to its id in `comment`.
+    pub author_name: &'a str,
+    #[serde(flatten)]
+    pub comment: &'a Comment,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventView<'a> {
+    #[serde(flatten)]
+    pub event: &'a AuditEvent,
+}
+
+/// An undone or redone write.
+#[derive(Debug, Serialize)]
+pub struct JournalView<'a> {
+    /// `undo` or `redo`.
+    pub action: &'static str,
+    /// When the write was made.
+    pub at: DateTime<Utc>,
+    pub actor: Option<&'a str>,
+    /// Epics, stories and sub-tasks it changed.
+    pub items: Vec<u32>,
+}
+
+impl<'a> JournalView<'a> {
+    pub fn new(action: &'static str, entry: &'a JournalEntry) -> Self {
+        Self {
+            action,
+            at: entry.at,
+            actor: entry.actor.as_deref(),
+            items: entry.item_ids(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrashView<'a> {
+    pub id: u32,
+    #[serde(flatten)]
+    pub entry: &'a TrashEntry,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedView {
+    pub id: u32,
+}
+
+const LIST_HEADER: &str =
+    "     id     |               name               |      status      |   priority   |    labels    |    due     ";
+const USER_HEADER: &str = "     id     |             username             |       name       ";
+const COMMENT_HEADER: &str =
+    "  id  |    author    |     created      |                  body                  ";
+const EVENT_HEADER: &str =
+    "       when       |    who     |  item  |    field     |        old         |        new         ";
+const TRASH_HEADER: &str =
+    "     id     |  type  |               name               |     deleted      ";
+const DETAIL_HEADER: &str =
+    "  id  |     name     |         description         |    status    |   priority   ";
+
+pub trait TableView {
+    /// Column titles above `table_row`s.
+    const HEADER: &'static str = LIST_HEADER;
+
+    fn table_row(&self) -> String;
+    fn table_detail(&self) -> String;
+}
+
+impl TableView for StoryView<'_> {
+    fn table_row(&self) -> String {
+        list_row(
+            self.id,
+            &self.story.name,
+            &self.story.status,
+            self.story.priority,
+            &self.story.labels,
+            self.story.due_date,
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        format!(
+            "{}\n{}",
+            detail_row(
+                self.id,
+                &self.story.name,
+                &self.story.description,
+                &self.story.status,
+                self.story.priority,
+            ),
+            dates_line(
+                self.story.created_at,
+                self.story.updated_at,
+                self.story.due_date
+            )
+        )
+    }
+}
+
+impl TableView for EpicView<'_> {
+    fn table_row(&self) -> String {
+        list_row(
+            self.id,
+            self.name,
+            self.status,
+            self.priority,
+            self.labels,
+            self.due_date,
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        format!(
+            "{}\n{}\npoints: {} total | {} completed | {} remaining\n\n{}",
+            detail_row(
+                self.id,
+                self.name,
+                self.description,
+                self.status,
+                self.priority
+            ),
+            dates_line(self.created_at, self.updated_at, self.due_date),
+            self.points.total,
+            self.points.completed,
+            self.points.remaining(),
+            render_table_list(&self.stories)
+        )
+    }
+}
+
+impl TableView for UserView<'_> {
+    const HEADER: &'static str = USER_HEADER;
+
+    fn table_row(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            get_column_string(&self.id.to_string(), 12),
+            get_column_string(&self.user.username, 34),
+            get_column_string(&self.user.name, 18),
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        format!("{}\n{}", Self::HEADER, self.table_row())
+    }
+}
+
+impl TableView for CommentView<'_> {
+    const HEADER: &'static str = COMMENT_HEADER;
+
+    fn table_row(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            get_column_string(&self.id.to_string(), 6),
+            get_column_string(self.author_name, 14),
+            get_column_string(&format_timestamp(Some(self.comment.created_at)), 18),
+            get_column_string(&self.comment.body.replace('\n', " "), 40),
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        format!("{}\n{}", Self::HEADER, self.table_row())
+    }
+}
+
+impl TableView for EventView<'_> {
+    const HEADER: &'static str = EVENT_HEADER;
+
+    fn table_row(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|{}",
+            get_column_string(&format_timestamp(Some(self.event.at)), 18),
+            get_column_string(self.event.actor.as_deref().unwrap_or(""), 12),
+            get_column_string(&self.event.item_id.to_string(), 8),
+            get_column_string(&self.event.field, 14),
+            get_column_string(&self.event.old, 20),
+            get_column_string(&self.event.new, 20),
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        format!("{}\n{}", Self::HEADER, self.table_row())
+    }
+}
+
+impl TableView for JournalView<'_> {
+    fn table_row(&self) -> String {
+        let verb = if self.action == "undo" {
+            "Undid"
+        } else {
+            "Redid"
+        };
+        format!(
+            "{} the change of {} by {}.",
+            verb,
+            format_timestamp(Some(self.at)),
+            self.actor.unwrap_or("nobody")
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        self.table_row()
+    }
+}
+
+impl TableView for TrashView<'_> {
+    const HEADER: &'static str = TRASH_HEADER;
+
+    fn table_row(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            get_column_string(&self.id.to_string(), 12),
+            get_column_string(self.entry.kind(), 8),
+            get_column_string(self.entry.name(), 34),
+            get_column_string(&format_timestamp(Some(self.entry.deleted_at)), 18),
+        )
+    }
+
+    fn table_detail(&self) -> String {
+        format!("{}\n{}", Self::HEADER, self.table_row())
+    }
+}
+
+impl TableView for CreatedView {
+    fn table_row(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn table_detail(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+/// Renders a list of items; JSON output is an array.
+pub fn render_list<T: Serialize + TableView>(
+    format: Format,
+    items: &[T],
+) -> Result<String, CliError> {
+    match format {
+        Format::Table => Ok(render_table_list(items) + "\n"),
+        Format::Json => render_json(items),
+        Format::Ndjson => render_ndjson(items),
+    }
+}
+
+/// Renders a single item; JSON output is an object.
+pub fn render_one<T: Serialize + TableView>(format: Format, item: &T) -> Result<String, CliError> {
+    match format {
+        Format::Table => Ok(item.table_detail() + "\n"),
+        Format::Json => render_json(item),
+        Format::Ndjson => render_ndjson(std::slice::from_ref(item)),
+    }
+}
+
+fn render_json<T: Serialize + ?Sized>(value: &T) -> Result<String, CliError> {
+    serde_json::to_string_pretty(value)
+        .map(|json| json + "\n")
+        .into_report()
+        .change_context(CliError::Format)
+}
+
+fn render_table_list<T: TableView>(items: &[T]) -> String {
+    std::iter::once(T::HEADER.to_owned())
+        .chain(items.iter().map(TableView::table_row))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One line per item, each ending in a newline, so an empty list is empty.
+fn render_ndjson<T: Serialize>(items: &[T]) -> Result<String, CliError> {
+    items
+        .iter()
+        .map(|item| {
+            serde_json::to_string(item)
+                .map(|line| line + "\n")
+                .into_report()
+                .change_context(CliError::Format)
+        })
+        .collect()
+}
+
+fn list_row(
+    id: u32,
+    name: &str,
+    status: &Status,
+    priority: Priority,
+    labels: &BTreeSet<String>,
+    due_date: Option<NaiveDate>,
+) -> String {
+    format!(
+        "{}|{}|{}|{}|{}|{}",
+        get_column_string(&id.to_string(), 12),
+        get_column_string(name, 34),
+        get_column_string(&status.to_string(), 18),
+        get_column_string(&priority.to_string(), 14),
+        get_column_string(&join_labels(labels), 14),
+        get_column_string(&format_date(due_date), 12),
+    )
+}
+
+fn dates_line(
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+    due_date: Option<NaiveDate>,
+) -> String {
+    format!(
+        "created: {} | updated: {} | due: {}",
+        format_timestamp(created_at),
+        format_timestamp(updated_at),
+        format_date(due_date)
+    )
+}
+
+fn detail_row(
+    id: u32,
+    name: &str,
+    description: &str,
+    status: &Status,
+    priority: Priority,
+) -> String {
+    format!(
+        "{}\n{}|{}|{}|{}|{}",
+        DETAIL_HEADER,
+        get_column_string(&id.to_string(), 6),
+        get_column_string(name, 14),
+        get_column_string(description, 29),
+        get_column_string(&status.to_string(), 14),
+        get_column_string(&priority.to_string(), 14)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn sample_state() -> DBState {
+        let mut epic = Epic::new("epic".to_owned(), "".to_owned());
+        epic.stories = vec![3, 2];
+        epic.labels = BTreeSet::from(["backend".to_owned()]);
+        let mut two = Story::new("two".to_owned(), "".to_owned());
+        two.points = Some(3);
+        two.status = Status::Resolved;
+        let mut three = Story::new("three".to_owned(), "".to_owned());
+        three.points = Some(5);
+
+        DBState {
+            schema_version: crate::db::CURRENT_SCHEMA_VERSION,
+            last_item_id: 3,
+            last_user_id: 0,
+            last_comment_id: 0,
+            last_link_id: 0,
+            revision: 0,
+            epics: HashMap::from([(1, epic)]),
+            stories: HashMap::from([(2, two), (3, three)]),
+            subtasks: HashMap::new(),
+            users: HashMap::new(),
+            comments: HashMap::new(),
+            links: HashMap::new(),
+            history: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            trash: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn render_one_should_resolve_epic_stories_in_json() {
+        let db_state = sample_state();
+        let view = EpicView::new(1, &db_state.epics[&1], &db_state, &Workflow::default());
+
+        let json = render_one(Format::Json, &view).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["id"], 1);
+        assert_eq!(value["stories"][0]["id"], 2);
+        assert_eq!(value["stories"][0]["name"], "two");
+        assert_eq!(value["stories"][1]["id"], 3);
+        assert_eq!(value["stories"][1]["status"], "Open");
+        assert_eq!(value["points"]["total"], 8);
+        assert_eq!(value["points"]["completed"], 3);
+        assert_eq!(value["labels"][0], "backend");
+    }
+
+    #[test]
+    fn epic_view_should_cap_point_totals() {
+        let mut db_state = sample_state();
+        for story in db_state.stories.values_mut() {
+            story.points = Some(u32::MAX);
+        }
+
+        let view = EpicView::new(1, &db_state.epics[&1], &db_state, &Workflow::default());
+
+        assert_eq!(view.points.total, u32::MAX);
+        assert_eq!(view.points.completed, u32::MAX);
+        assert_eq!(view.points.remaining(), 0);
+    }
+
+    #[test]
+    fn render_list_should_write_one_object_per_line_in_ndjson() {
+        let db_state = sample_state();
+        let views = vec![
+            StoryView {
+                id: 2,
+                story: &db_state.stories[&2],
+            },
+            StoryView {
+                id: 3,
+                story: &db_state.stories[&3],
+            },
+        ];
+
+        let ndjson = render_list(Format::Ndjson, &views).unwrap();
+        let lines = ndjson.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        let value: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(value["id"], 3);
+    }
+
+    #[test]
+    fn render_list_should_print_nothing_for_empty_ndjson() {
+        let views: Vec<StoryView> = vec![];
+        assert_eq!(render_list(Format::Ndjson, &views).unwrap(), "");
+    }
+
+    #[test]
+    fn render_list_should_draw_table() {
+        let db_state = sample_state();
+        let views = vec![EpicView::new(
+            1,
+            &db_state.epics[&1],
+            &db_state,
+            &Workflow::default(),
+        )];
+
+        let table = render_list(Format::Table, &views).unwrap();
+
+        assert_eq!(table.lines().count(), 2);
+        assert!(table
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("1           |epic"));
+        assert!(table.lines().nth(1).unwrap().contains("|backend"));
+    }
+}
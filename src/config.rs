@@ -0,0 +1,531 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use error_stack::{IntoReport, Result, ResultExt};
+
+use crate::{
+    db::DatabaseBackend,
+    models::{Invariants, Status, Workflow, Workflows},
+};
+
+/// Name of the per-project config file, looked up from the working
+/// directory towards the filesystem root.
+pub const PROJECT_CONFIG_FILE: &str = ".jira.toml";
+
+const USER_CONFIG_DIR: &str = "bootcamp_jira";
+const USER_CONFIG_FILE: &str = "config.toml";
+const USER_DATABASE_FILE: &str = "db.json";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read,
+    Parse,
+    InvalidValue,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let m = match self {
+            ConfigError::Read => "Failed to read config file.",
+            ConfigError::Parse => "Failed to parse config file.",
+            ConfigError::InvalidValue => "Invalid config value.",
+        };
+        write!(f, "{}", m)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Fully resolved settings.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+    pub database_path: String,
+    /// Storage backend; `None` picks it from the database file extension.
+    pub backend: Option<DatabaseBackend>,
+    pub clear_screen: bool,
+    /// Write names and descriptions in `$VISUAL`/`$EDITOR` instead of
+    /// single-line prompts.
+    pub use_editor: bool,
+    /// Username of whoever runs the tracker, used as reporter of new items
+    /// and for the "assigned to me" view.
+    pub current_user: Option<String>,
+    pub workflows: Workflows,
+    pub invariants: Invariants,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_path: default_database_path(),
+            backend: None,
+            clear_screen: true,
+            use_editor: false,
+            current_user: None,
+            workflows: Workflows::default(),
+            invariants: Invariants::default(),
+        }
+    }
+}
+
+/// One source of settings. Unset fields leave the lower layers untouched.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    #[serde(default)]
+    pub database: DatabaseLayer,
+    #[serde(default)]
+    pub ui: UiLayer,
+    #[serde(default)]
+    pub user: UserLayer,
+    #[serde(default)]
+    pub workflow: WorkflowsLayer,
+    #[serde(default)]
+    pub rules: RulesLayer,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseLayer {
+    pub path: Option<String>,
+    pub backend: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UiLayer {
+    pub clear_screen: Option<bool>,
+    pub editor: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserLayer {
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowsLayer {
+    pub epic: Option<WorkflowLayer>,
+    pub story: Option<WorkflowLayer>,
+}
+
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesLayer {
+    /// allow, block or cascade.
+    pub close_epic: Option<String>,
+    pub start_epic_with_story: Option<bool>,
+}
+
+/// A workflow replaces the one of lower layers as a whole.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowLayer {
+    pub states: Vec<String>,
+    /// Allowed next states by state; unlisted states may move to any state.
+    #[serde(default)]
+    pub transitions: HashMap<String, Vec<String>>,
+    /// States that count as done; resolved and closed when not given.
+    #[serde(default)]
+    pub done: Option<Vec<String>>,
+}
+
+impl WorkflowLayer {
+    fn to_workflow(&self) -> Result<Workflow, ConfigError> {
+        let parse = |name: &String| {
+            name.parse::<Status>()
+                .map_err(|_| ConfigError::InvalidValue)
+                .into_report()
+                .attach_printable(name.clone())
+        };
+        let states = self
+            .states
+            .iter()
+            .map(parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if states.is_empty() {
+            return Err(ConfigError::InvalidValue)
+                .into_report()
+                .attach_printable("A workflow needs at least one state");
+        }
+        let mut transitions = HashMap::new();
+        for (from, to) in &self.transitions {
+            let from = parse(from)?;
+            let to = to.iter().map(parse).collect::<Result<Vec<_>, _>>()?;
+            if let Some(unknown) = std::iter::once(&from)
+                .chain(&to)
+                .find(|state| !states.contains(state))
+            {
+                return Err(ConfigError::InvalidValue)
+                    .into_report()
+                    .attach_printable(format!("{} is not one of the workflow states", unknown));
+            }
+            transitions.insert(from, to);
+        }
+        let done = match &self.done {
+            Some(done) => done.iter().map(parse).collect::<Result<Vec<_>, _>>()?,
+            None => Workflow::default()
+                .done
+                .into_iter()
+                .filter(|state| states.contains(state))
+                .collect(),
+        };
+        if let Some(unknown) = done.iter().find(|state| !states.contains(state)) {
+            return Err(ConfigError::InvalidValue)
+                .into_report()
+                .attach_printable(format!("{} is not one of the workflow states", unknown));
+        }
+
+        Ok(Workflow {
+            done,
+            states,
+            transitions,
+        })
+    }
+}
+
+impl ConfigLayer {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let raw_content = std::fs::read_to_string(path)
+            .into_report()
+            .change_context(ConfigError::Read)
+            .attach_printable_lazy(|| path.display().to_string())?;
+
+        toml::from_str(&raw_content)
+            .into_report()
+            .change_context(ConfigError::Parse)
+            .attach_printable_lazy(|| path.display().to_string())
+    }
+
+    /// Reads `JIRA_DB_PATH`, `JIRA_BACKEND`, `JIRA_CLEAR_SCREEN`,
+    /// `JIRA_USE_EDITOR` and `JIRA_USER`.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let bool_var = |name: &str| match var(name) {
+            Some(value) => parse_bool(&value)
+                .ok_or(ConfigError::InvalidValue)
+                .into_report()
+                .attach_printable(name.to_owned())
+                .map(Some),
+            None => Ok(None),
+        };
+        let clear_screen = bool_var("JIRA_CLEAR_SCREEN")?;
+        let editor = bool_var("JIRA_USE_EDITOR")?;
+
+        Ok(Self {
+            database: DatabaseLayer {
+                path: var("JIRA_DB_PATH"),
+                backend: var("JIRA_BACKEND"),
+            },
+            ui: UiLayer {
+                clear_screen,
+                editor,
+            },
+            user: UserLayer {
+                username: var("JIRA_USER"),
+            },
+            workflow: WorkflowsLayer::default(),
+            rules: RulesLayer::default(),
+        })
+    }
+}
+
+impl Config {
+    /// Builds the config from, in increasing priority: built-in defaults, the
+    /// per-user config file, the nearest per-project `.jira.toml`, environment
+    /// variables and `cli` overrides.
+    pub fn load(cli: ConfigLayer) -> Result<Self, ConfigError> {
+        let user_file =
+            dirs::config_dir().map(|dir| dir.join(USER_CONFIG_DIR).join(USER_CONFIG_FILE));
+        let current_dir = std::env::current_dir()
+            .into_report()
+            .change_context(ConfigError::Read)?;
+        let env = ConfigLayer::from_env(|name| std::env::var(name).ok())?;
+
+        Self::load_from(user_file.as_deref(), &current_dir, env, cli)
+    }
+
+    pub fn load_from(
+        user_file: Option<&Path>,
+        current_dir: &Path,
+        env: ConfigLayer,
+        cli: ConfigLayer,
+    ) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+
+        if let Some(user_file) = user_file.filter(|path| path.is_file()) {
+            config.apply(ConfigLayer::from_file(user_file)?, user_file.parent())?;
+        }
+        if let Some(project_file) = find_project_file(current_dir) {
+            config.apply(
+                ConfigLayer::from_file(&project_file)?,
+                project_file.parent(),
+            )?;
+        }
+        config.apply(env, Some(current_dir))?;
+        config.apply(cli, Some(current_dir))?;
+
+        Ok(config)
+    }
+
+    /// Overrides the settings present in `layer`. Relative database paths are
+    /// resolved against `base_dir`, the directory of the file they came from.
+    fn apply(&mut self, layer: ConfigLayer, base_dir: Option<&Path>) -> Result<(), ConfigError> {
+        if let Some(path) = layer.database.path {
+            self.database_path = match base_dir {
+                Some(base_dir) if Path::new(&path).is_relative() => {
+                    base_dir.join(path).to_string_lossy().into_owned()
+                }
+                _ => path,
+            };
+        }
+        if let Some(backend) = layer.database.backend {
+            self.backend = Some(
+                backend
+                    .parse()
+                    .map_err(|_| ConfigError::InvalidValue)
+                    .into_report()
+                    .attach_printable(backend)?,
+            );
+        }
+        if let Some(clear_screen) = layer.ui.clear_screen {
+            self.clear_screen = clear_screen;
+        }
+        if let Some(editor) = layer.ui.editor {
+            self.use_editor = editor;
+        }
+        if let Some(username) = layer.user.username {
+            self.current_user = Some(username);
+        }
+        if let Some(workflow) = layer.workflow.epic {
+            self.workflows.epic = workflow.to_workflow()?;
+        }
+        if let Some(workflow) = layer.workflow.story {
+            self.workflows.story = workflow.to_workflow()?;
+        }
+        if let Some(close_epic) = layer.rules.close_epic {
+            self.invariants.close_epic = close_epic
+                .parse()
+                .map_err(|_| ConfigError::InvalidValue)
+                .into_report()
+                .attach_printable(close_epic)?;
+        }
+        if let Some(start_epic_with_story) = layer.rules.start_epic_with_story {
+            self.invariants.start_epic_with_story = start_epic_with_story;
+        }
+
+        Ok(())
+    }
+}
+
+fn find_project_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+/// The per-user data directory, so the tracker finds the same database from
+/// any working directory. Falls back to a relative path where the platform
+/// has no such directory.
+fn default_database_path() -> String {
+    dirs::data_dir()
+        .map(|dir| dir.join(USER_CONFIG_DIR).join(USER_DATABASE_FILE))
+        .unwrap_or_else(|| Path::new("data").join(USER_DATABASE_FILE))
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CloseEpicRule;
+
+    fn no_env() -> ConfigLayer {
+        ConfigLayer::from_env(|_| None).unwrap()
+    }
+
+    #[test]
+    fn load_should_use_defaults_without_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load_from(None, dir.path(), no_env(), ConfigLayer::default());
+
+        assert_eq!(config.unwrap(), Config::default());
+    }
+
+    #[test]
+    fn default_database_path_should_be_absolute() {
+        let path = Config::default().database_path;
+
+        assert!(Path::new(&path).is_absolute());
+        assert!(Path::new(&path).ends_with(Path::new(USER_CONFIG_DIR).join(USER_DATABASE_FILE)));
+    }
+
+    #[test]
+    fn load_should_layer_sources_in_priority_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let user_file = dir.path().join("user.toml");
+        std::fs::write(
+            &user_file,
+            "[database]\npath = \"/user/db.json\"\nbackend = \"json\"\n[ui]\nclear_screen = false\neditor = true\n[user]\nusername = \"jdoe\"\n",
+        )
+        .unwrap();
+
+        let project_dir = dir.path().join("project");
+        let nested_dir = project_dir.join("src/nested");
+        std::fs::create_dir_all(&nested_dir).unwrap();
+        std::fs::write(
+            project_dir.join(PROJECT_CONFIG_FILE),
+            "[database]\npath = \"tracker.sqlite\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from(
+            Some(&user_file),
+            &nested_dir,
+            no_env(),
+            ConfigLayer::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.database_path,
+            project_dir.join("tracker.sqlite").to_string_lossy()
+        );
+        assert_eq!(config.backend, Some(DatabaseBackend::Json));
+        assert!(!config.clear_screen);
+        assert!(config.use_editor);
+        assert_eq!(config.current_user.as_deref(), Some("jdoe"));
+
+        let env = ConfigLayer::from_env(|name| match name {
+            "JIRA_BACKEND" => Some("sqlite".to_owned()),
+            "JIRA_CLEAR_SCREEN" => Some("yes".to_owned()),
+            "JIRA_USE_EDITOR" => Some("off".to_owned()),
+            "JIRA_USER" => Some("asmith".to_owned()),
+            _ => None,
+        })
+        .unwrap();
+        let mut cli = ConfigLayer::default();
+        cli.database.path = Some("/cli/db.json".to_owned());
+
+        let config = Config::load_from(Some(&user_file), &nested_dir, env, cli).unwrap();
+
+        assert_eq!(config.database_path, "/cli/db.json");
+        assert_eq!(config.backend, Some(DatabaseBackend::SQLite));
+        assert!(config.clear_screen);
+        assert!(!config.use_editor);
+        assert_eq!(config.current_user.as_deref(), Some("asmith"));
+    }
+
+    #[test]
+    fn load_should_reject_invalid_values() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[database]\nbackend = \"mongo\"\n",
+        )
+        .unwrap();
+
+        assert!(Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).is_err());
+        assert!(ConfigLayer::from_env(|_| Some("maybe".to_owned())).is_err());
+
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[workflow.story]\nstates = [\"open\", \"closed\"]\ntransitions = { open = [\"review\"] }\n",
+        )
+        .unwrap();
+        assert!(Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).is_err());
+    }
+
+    #[test]
+    fn load_should_read_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[rules]\nclose_epic = \"cascade\"\nstart_epic_with_story = true\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).unwrap();
+
+        assert_eq!(config.invariants.close_epic, CloseEpicRule::Cascade);
+        assert!(config.invariants.start_epic_with_story);
+
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[rules]\nclose_epic = \"never\"\n",
+        )
+        .unwrap();
+        assert!(Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).is_err());
+    }
+
+    #[test]
+    fn load_should_read_workflows() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[workflow.story]\nstates = [\"open\", \"in-progress\", \"review\", \"closed\"]\n[workflow.story.transitions]\nopen = [\"in-progress\"]\nin-progress = [\"review\"]\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).unwrap();
+
+        assert_eq!(config.workflows.epic, Workflow::default());
+        assert_eq!(config.invariants, Invariants::default());
+        let review = Status::Custom("review".to_owned());
+        assert_eq!(config.workflows.story.states[2], review);
+        assert_eq!(
+            config.workflows.story.next_states(&Status::InProgress),
+            vec![review.clone()]
+        );
+        assert_eq!(
+            config.workflows.story.next_states(&review),
+            vec![Status::Open, Status::InProgress, Status::Closed]
+        );
+        assert_eq!(config.workflows.story.done, vec![Status::Closed]);
+    }
+
+    #[test]
+    fn load_should_read_done_states() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(
+            &path,
+            "[workflow.story]\nstates = [\"todo\", \"doing\", \"done\"]\ndone = [\"done\"]\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).unwrap();
+
+        let done = Status::Custom("done".to_owned());
+        assert!(config.workflows.story.is_done(&done));
+        assert!(!config.workflows.story.is_done(&Status::Closed));
+
+        std::fs::write(
+            &path,
+            "[workflow.story]\nstates = [\"todo\", \"done\"]\ndone = [\"shipped\"]\n",
+        )
+        .unwrap();
+        assert!(Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).is_err());
+    }
+
+    #[test]
+    fn load_should_reject_empty_workflows() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[workflow.epic]\nstates = []\n",
+        )
+        .unwrap();
+
+        assert!(Config::load_from(None, dir.path(), no_env(), ConfigLayer::default()).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use chrono::{DateTime, Utc};
+
+use crate::models::{format_date, join_labels, AuditEvent, DBState, Epic, Story, SubTask};
+
+/// Audit events turning `before` into `after`, epics, stories and sub-tasks
+/// in id order.
+pub fn diff(
+    before: &DBState,
+    after: &DBState,
+    actor: Option<&str>,
+    at: DateTime<Utc>,
+) -> Vec<AuditEvent> {
+    let event = |item_id: u32, field: &str, old: String, new: String| AuditEvent {
+        at,
+        actor: actor.map(str::to_owned),
+        item_id,
+        field: field.to_owned(),
+        old,
+        new,
+    };
+
+    let mut ids = before
+        .epics
+        .keys()
+        .chain(after.epics.keys())
+        .chain(before.stories.keys())
+        .chain(after.stories.keys())
+        .chain(before.subtasks.keys())
+        .chain(after.subtasks.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+
+    let mut events = vec![];
+    for id in ids {
+        let old_fields = item_fields(before, id);
+        let new_fields = item_fields(after, id);
+        match (old_fields, new_fields) {
+            (None, Some(fields)) => {
+                let field = if before.is_trashed(id) {
+                    "restored"
+                } else {
+                    "created"
+                };
+                events.push(event(id, field, String::new(), fields[0].1.clone()))
+            }
+            (Some(fields), None) => {
+                events.push(event(id, "deleted", fields[0].1.clone(), String::new()))
+            }
+            (Some(old_fields), Some(new_fields)) => {
+                for ((field, old), (_, new)) in old_fields.into_iter().zip(new_fields) {
+                    if old != new {
+                        events.push(event(id, field, old, new));
+                    }
+                }
+            }
+            (None, None) => (),
+        }
+    }
+
+    let mut comment_ids = before
+        .comments
+        .keys()
+        .chain(after.comments.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    comment_ids.sort();
+    comment_ids.dedup();
+
+    for id in comment_ids {
+        match (before.comments.get(&id), after.comments.get(&id)) {
+            // Comments restored with their item go with its `restored` event.
+            (None, Some(comment))
+                if !before
+                    .trash
+                    .values()
+                    .any(|entry| entry.comments.contains_key(&id)) =>
+            {
+                events.push(event(
+                    comment.item_id,
+                    "comment",
+                    String::new(),
+                    comment.body.clone(),
+                ))
+            }
+            // Comments of deleted items go with the item's own event.
+            (Some(comment), None) if item_fields(after, comment.item_id).is_some() => {
+                events.push(event(
+                    comment.item_id,
+                    "comment",
+                    comment.body.clone(),
+                    String::new(),
+                ))
+            }
+            (Some(old), Some(new)) if old.body != new.body => events.push(event(
+                new.item_id,
+                "comment",
+                old.body.clone(),
+                new.body.clone(),
+            )),
+            _ => (),
+        }
+    }
+
+    let mut link_ids = before
+        .links
+        .keys()
+        .chain(after.links.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    link_ids.sort();
+    link_ids.dedup();
+
+    // Both stories of a link record it, each from its own side.
+    for id in link_ids {
+        match (before.links.get(&id), after.links.get(&id)) {
+            (None, Some(link)) => {
+                for story_id in [link.from, link.to] {
+                    events.push(event(
+                        story_id,
+                        "link",
+                        String::new(),
+                        link.describe(story_id),
+                    ));
+                }
+            }
+            (Some(link), None) => {
+                for story_id in [link.from, link.to] {
+                    events.push(event(
+                        story_id,
+                        "link",
+                        link.describe(story_id),
+                        String::new(),
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    events
+}
+
+/// Audited fields of the epic, story or sub-task `id` as `(field, value)`,
+/// name first.
+fn item_fields(db_state: &DBState, id: u32) -> Option<Vec<(&'static str, String)>> {
+    if let Some(epic) = db_state.epics.get(&id) {
+        Some(epic_fields(db_state, epic))
+    } else if let Some(story) = db_state.stories.get(&id) {
+        Some(story_fields(db_state, story))
+    } else {
+        db_state.subtasks.get(&id).map(subtask_fields)
+    }
+}
+
+fn epic_fields(db_state: &DBState, epic: &Epic) -> Vec<(&'static str, String)> {
+    vec![
+        ("name", epic.name.clone()),
+        ("description", epic.description.clone()),
+        ("status", epic.status.to_string()),
+        ("priority", epic.priority.to_string()),
+        ("assignee", db_state.username(epic.assignee).to_owned()),
+        ("reporter", db_state.username(epic.reporter).to_owned()),
+        ("labels", join_labels(&epic.labels)),
+        ("due_date", format_date(epic.due_date)),
+        (
+            "stories",
+            epic.stories
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+    ]
+}
+
+fn story_fields(db_state: &DBState, story: &Story) -> Vec<(&'static str, String)> {
+    vec![
+        ("name", story.name.clone()),
+        ("description", story.description.clone()),
+        ("status", story.status.to_string()),
+        ("priority", story.priority.to_string()),
+        (
+            "points",
+            story
+                .points
+                .map_or(String::new(), |points| points.to_string()),
+        ),
+        ("assignee", db_state.username(story.assignee).to_owned()),
+        ("reporter", db_state.username(story.reporter).to_owned()),
+        ("labels", join_labels(&story.labels)),
+        ("due_date", format_date(story.due_date)),
+        (
+            "subtasks",
+            story
+                .subtasks
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        ),
+    ]
+}
+
+fn subtask_fields(subtask: &SubTask) -> Vec<(&'static str, String)> {
+    vec![
+        ("name", subtask.name.clone()),
+        ("description", subtask.description.clone()),
+        ("status", subtask.status.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Status;
+
+    #[test]
+    fn diff_should_record_changed_fields() {
+        let before = DBState::sample();
+        let mut after = before.clone();
+        after.stories.get_mut(&2).unwrap().status = Status::Closed;
+        after.epics.get_mut(&1).unwrap().stories = vec![2];
+        after.stories.remove(&3);
+
+        let events = diff(&before, &after, Some("jdoe"), Utc::now());
+
+        let summary = events
+            .iter()
+            .map(|event| {
+                (
+                    event.item_id,
+                    event.field.as_str(),
+                    event.old.as_str(),
+                    event.new.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "stories", "2,3", "2"),
+                (2, "status", "OPEN", "CLOSED"),
+                (3, "deleted", "Story - Project 1 README", ""),
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|event| event.actor.as_deref() == Some("jdoe")));
+    }
+
+    #[test]
+    fn diff_should_be_empty_for_unchanged_state() {
+        let state = DBState::sample();
+        assert!(diff(&state, &state, None, Utc::now()).is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::models::{Change, DBState, JournalEntry};
+
+/// The journal entry turning `before` into `after`, or `None` when no epic,
+/// story, sub-task, comment, link, user or trash entry changed. Changes hold
+/// whole copies of the items, so deleting an epic stores it with its stories
+/// and their comments; `push_journal_entry` caps the size of the stacks.
+pub fn diff(before: &DBState, after: &DBState, actor: Option<&str>) -> Option<JournalEntry> {
+    let entry = JournalEntry {
+        at: Utc::now(),
+        actor: actor.map(str::to_owned),
+        epics: diff_items(&before.epics, &after.epics),
+        stories: diff_items(&before.stories, &after.stories),
+        subtasks: diff_items(&before.subtasks, &after.subtasks),
+        comments: diff_items(&before.comments, &after.comments),
+        links: diff_items(&before.links, &after.links),
+        users: diff_items(&before.users, &after.users),
+        trash: diff_items(&before.trash, &after.trash),
+    };
+    let empty = entry.epics.is_empty()
+        && entry.stories.is_empty()
+        && entry.subtasks.is_empty()
+        && entry.comments.is_empty()
+        && entry.links.is_empty()
+        && entry.users.is_empty()
+        && entry.trash.is_empty();
+    (!empty).then_some(entry)
+}
+
+/// Folds the later `entry` into `into`, so both writes are undone as one.
+/// Items changed by both keep the value from before the first write and after
+/// the second; items that end up as they started are dropped.
+pub fn merge(into: &mut JournalEntry, entry: JournalEntry) {
+    into.at = entry.at;
+    merge_items(&mut into.epics, entry.epics);
+    merge_items(&mut into.stories, entry.stories);
+    merge_items(&mut into.subtasks, entry.subtasks);
+    merge_items(&mut into.comments, entry.comments);
+    merge_items(&mut into.links, entry.links);
+    merge_items(&mut into.users, entry.users);
+    merge_items(&mut into.trash, entry.trash);
+}
+
+/// Restores the values from before the write of `entry`.
+pub fn revert(entry: &JournalEntry, db_state: &mut DBState) {
+    apply_items(&entry.epics, &mut db_state.epics, false);
+    apply_items(&entry.stories, &mut db_state.stories, false);
+    apply_items(&entry.subtasks, &mut db_state.subtasks, false);
+    apply_items(&entry.comments, &mut db_state.comments, false);
+    apply_items(&entry.links, &mut db_state.links, false);
+    apply_items(&entry.users, &mut db_state.users, false);
+    apply_items(&entry.trash, &mut db_state.trash, false);
+}
+
+/// Repeats the write of `entry`.
+pub fn replay(entry: &JournalEntry, db_state: &mut DBState) {
+    apply_items(&entry.epics, &mut db_state.epics, true);
+    apply_items(&entry.stories, &mut db_state.stories, true);
+    apply_items(&entry.subtasks, &mut db_state.subtasks, true);
+    apply_items(&entry.comments, &mut db_state.comments, true);
+    apply_items(&entry.links, &mut db_state.links, true);
+    apply_items(&entry.users, &mut db_state.users, true);
+    apply_items(&entry.trash, &mut db_state.trash, true);
+}
+
+/// Ids of the items changed by `entry` whose value in `db_state` is no
+/// longer the one it left behind, or with `forward` the one it started from;
+/// a later write changed them too.
+pub fn conflicts(entry: &JournalEntry, db_state: &DBState, forward: bool) -> Vec<u32> {
+    let mut ids = (conflicting_items(&entry.epics, &db_state.epics, forward))
+        .chain(conflicting_items(
+            &entry.stories,
+            &db_state.stories,
+            forward,
+        ))
+        .chain(conflicting_items(
+            &entry.subtasks,
+            &db_state.subtasks,
+            forward,
+        ))
+        .chain(conflicting_items(
+            &entry.comments,
+            &db_state.comments,
+            forward,
+        ))
+        .chain(conflicting_items(&entry.links, &db_state.links, forward))
+        .chain(conflicting_items(&entry.users, &db_state.users, forward))
+        .chain(conflicting_items(&entry.trash, &db_state.trash, forward))
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+fn diff_items<T: PartialEq + Clone>(
+    before: &HashMap<u32, T>,
+    after: &HashMap<u32, T>,
+) -> Vec<Change<T>> {
+    let mut ids = before
+        .keys()
+        .chain(after.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids.dedup();
+
+    ids.into_iter()
+        .filter(|id| before.get(id) != after.get(id))
+        .map(|id| Change {
+            id,
+            before: before.get(&id).cloned(),
+            after: after.get(&id).cloned(),
+        })
+        .collect()
+}
+
+fn merge_items<T: PartialEq>(into: &mut Vec<Change<T>>, changes: Vec<Change<T>>) {
+    for change in changes {
+        match into.iter_mut().find(|earlier| earlier.id == change.id) {
+            Some(earlier) => earlier.after = change.after,
+            None => into.push(change),
+        }
+    }
+    into.retain(|change| change.before != change.after);
+}
+
+fn conflicting_items<'a, T: PartialEq>(
+    changes: &'a [Change<T>],
+    items: &'a HashMap<u32, T>,
+    forward: bool,
+) -> impl Iterator<Item = u32> + 'a {
+    changes
+        .iter()
+        .filter(move |change| {
+            let expected = if forward {
+                &change.before
+            } else {
+                &change.after
+            };
+            items.get(&change.id) != expected.as_ref()
+        })
+        .map(|change| change.id)
+}
+
+fn apply_items<T: Clone>(changes: &[Change<T>], items: &mut HashMap<u32, T>, forward: bool) {
+    for change in changes {
+        let value = if forward {
+            &change.after
+        } else {
+            &change.before
+        };
+        match value {
+            Some(value) => items.insert(change.id, value.clone()),
+            None => items.remove(&change.id),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Status;
+
+    #[test]
+    fn revert_and_replay_should_round_trip() {
+        let before = DBState::sample();
+        let mut after = before.clone();
+        after.stories.get_mut(&2).unwrap().status = Status::Closed;
+        after.epics.get_mut(&1).unwrap().stories = vec![2];
+        after.stories.remove(&3);
+
+        let entry = diff(&before, &after, None).unwrap();
+        assert_eq!(entry.epics.len(), 1);
+        assert_eq!(entry.stories.len(), 2);
+
+        let mut state = after.clone();
+        revert(&entry, &mut state);
+        assert_eq!(state, before);
+
+        replay(&entry, &mut state);
+        assert_eq!(state, after);
+    }
+
+    #[test]
+    fn merge_should_span_both_writes() {
+        let before = DBState::sample();
+        let mut middle = before.clone();
+        middle.stories.get_mut(&2).unwrap().status = Status::InProgress;
+        middle.stories.get_mut(&3).unwrap().status = Status::Closed;
+        let mut after = middle.clone();
+        after.stories.get_mut(&2).unwrap().status = Status::Resolved;
+        after.stories.get_mut(&3).unwrap().status = Status::Open;
+
+        let mut entry = diff(&before, &middle, None).unwrap();
+        merge(&mut entry, diff(&middle, &after, None).unwrap());
+
+        assert_eq!(entry.stories.len(), 1);
+        let mut state = after.clone();
+        revert(&entry, &mut state);
+        assert_eq!(state, before);
+        replay(&entry, &mut state);
+        assert_eq!(state, after);
+    }
+
+    #[test]
+    fn diff_should_be_none_for_unchanged_state() {
+        let state = DBState::sample();
+        assert!(diff(&state, &state, None).is_none());
+    }
+}
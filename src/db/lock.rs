@@ -0,0 +1,79 @@
+use std::{
+    fs::{File, OpenOptions},
+    time::{Duration, Instant},
+};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use fs2::FileExt;
+
+use super::DatabaseError;
+
+/// How long a session waits for another one to release the database.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Advisory, cross-process lock on a database file, released on drop.
+pub struct DatabaseLock {
+    file: Option<File>,
+}
+
+impl DatabaseLock {
+    /// A lock for backends that don't need one.
+    pub fn none() -> Self {
+        Self { file: None }
+    }
+
+    /// Locks `<file_path>.lock`, retrying until `timeout` elapses.
+    ///
+    /// A sibling file is locked rather than the database itself because the
+    /// database file gets replaced by rename on every write.
+    pub fn acquire(file_path: &str, timeout: Duration) -> Result<Self, DatabaseError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(format!("{}.lock", file_path))
+            .into_report()
+            .change_context(DatabaseError::LockError)?;
+
+        let start = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(Self { file: Some(file) }),
+                Err(e) if start.elapsed() >= timeout => {
+                    return Err(e)
+                        .into_report()
+                        .change_context(DatabaseError::LockError)
+                }
+                Err(_) => std::thread::sleep(LOCK_RETRY_INTERVAL),
+            }
+        }
+    }
+}
+
+impl Drop for DatabaseLock {
+    fn drop(&mut self) {
+        if let Some(file) = &self.file {
+            let _ = file.unlock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acquire_should_time_out_while_lock_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("db.json").to_string_lossy().into_owned();
+
+        let held = DatabaseLock::acquire(&file_path, DEFAULT_LOCK_TIMEOUT).unwrap();
+        assert!(DatabaseLock::acquire(&file_path, Duration::from_millis(100)).is_err());
+
+        drop(held);
+        assert!(DatabaseLock::acquire(&file_path, Duration::from_millis(100)).is_ok());
+    }
+}
@@ -0,0 +1,340 @@
+use error_stack::{IntoReport, Result};
+use serde_json::{json, Value};
+
+use super::DatabaseError;
+use crate::models::Priority;
+
+/// Schema version written by this build. Files without a `schema_version`
+/// field predate versioning and are treated as version 0.
+pub const CURRENT_SCHEMA_VERSION: u32 = 15;
+
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Value) -> Option<()>,
+}
+
+/// Ordered list of upgrade steps, each taking a file from `from` to `from + 1`.
+/// Steps that only fill defaults still bump the version, so that an older
+/// build refuses a file it would misread instead of silently dropping data.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "v0 -> v1: drop redundant `id` fields from epics and stories",
+        apply: drop_item_ids,
+    },
+    Migration {
+        from: 1,
+        description: "v1 -> v2: add the user directory and assignees and reporters",
+        apply: add_users,
+    },
+    Migration {
+        from: 2,
+        description: "v2 -> v3: add priorities to epics and stories",
+        apply: add_priorities,
+    },
+    Migration {
+        from: 3,
+        description: "v3 -> v4: add story points",
+        apply: add_points,
+    },
+    Migration {
+        from: 4,
+        description: "v4 -> v5: add labels to epics and stories",
+        apply: add_labels,
+    },
+    Migration {
+        from: 5,
+        description: "v5 -> v6: add created and updated timestamps",
+        apply: add_timestamps,
+    },
+    Migration {
+        from: 6,
+        description: "v6 -> v7: add due dates to epics and stories",
+        apply: add_due_dates,
+    },
+    Migration {
+        from: 7,
+        description: "v7 -> v8: add comments",
+        apply: add_comments,
+    },
+    Migration {
+        from: 8,
+        description: "v8 -> v9: add the change history",
+        apply: add_history,
+    },
+    Migration {
+        from: 9,
+        description: "v9 -> v10: add the undo and redo journal",
+        apply: add_journal,
+    },
+    Migration {
+        from: 10,
+        description: "v10 -> v11: add the trash",
+        apply: add_trash,
+    },
+    Migration {
+        from: 11,
+        description: "v11 -> v12: allow custom status names",
+        apply: allow_custom_statuses,
+    },
+    Migration {
+        from: 12,
+        description: "v12 -> v13: add sub-tasks to stories",
+        apply: add_subtasks,
+    },
+    Migration {
+        from: 13,
+        description: "v13 -> v14: add links between stories",
+        apply: add_links,
+    },
+    Migration {
+        from: 14,
+        description: "v14 -> v15: give users, comments and links their own id sequences",
+        apply: split_id_sequences,
+    },
+];
+
+pub fn schema_version(raw: &Value) -> u32 {
+    raw.get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |version| version as u32)
+}
+
+/// Upgrades `raw` in place to `CURRENT_SCHEMA_VERSION`, one step at a time,
+/// returning the description of every step applied.
+pub fn migrate(raw: &mut Value) -> Result<Vec<&'static str>, DatabaseError> {
+    let mut version = schema_version(raw);
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(DatabaseError::MigrationError).into_report();
+    }
+
+    let mut applied = vec![];
+    while version < CURRENT_SCHEMA_VERSION {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(DatabaseError::MigrationError)?;
+
+        (migration.apply)(raw).ok_or(DatabaseError::MigrationError)?;
+
+        version += 1;
+        raw.as_object_mut()
+            .ok_or(DatabaseError::MigrationError)?
+            .insert("schema_version".to_owned(), Value::from(version));
+        applied.push(migration.description);
+    }
+
+    Ok(applied)
+}
+
+fn drop_item_ids(raw: &mut Value) -> Option<()> {
+    for table in ["epics", "stories"] {
+        let Some(items) = raw.get_mut(table) else {
+            continue;
+        };
+        for item in items.as_object_mut()?.values_mut() {
+            item.as_object_mut()?.remove("id");
+        }
+    }
+    Some(())
+}
+
+/// Sets `field` on every object in the `table` map that lacks it.
+fn fill_items(raw: &mut Value, table: &str, field: &str, default: Value) -> Option<()> {
+    let Some(items) = raw.get_mut(table) else {
+        return Some(());
+    };
+    for item in items.as_object_mut()?.values_mut() {
+        item.as_object_mut()?
+            .entry(field)
+            .or_insert_with(|| default.clone());
+    }
+    Some(())
+}
+
+/// Sets `field` on every undo and redo journal entry that lacks it.
+fn fill_journal(raw: &mut Value, field: &str, default: Value) -> Option<()> {
+    for stack in ["undo", "redo"] {
+        let Some(entries) = raw.get_mut(stack) else {
+            continue;
+        };
+        for entry in entries.as_array_mut()? {
+            entry
+                .as_object_mut()?
+                .entry(field)
+                .or_insert_with(|| default.clone());
+        }
+    }
+    Some(())
+}
+
+fn fill_top(raw: &mut Value, field: &str, default: Value) -> Option<()> {
+    raw.as_object_mut()?.entry(field).or_insert(default);
+    Some(())
+}
+
+fn add_users(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "users", json!({}))?;
+    for table in ["epics", "stories"] {
+        fill_items(raw, table, "assignee", Value::Null)?;
+        fill_items(raw, table, "reporter", Value::Null)?;
+    }
+    Some(())
+}
+
+fn add_priorities(raw: &mut Value) -> Option<()> {
+    let default = serde_json::to_value(Priority::default()).ok()?;
+    for table in ["epics", "stories"] {
+        fill_items(raw, table, "priority", default.clone())?;
+    }
+    Some(())
+}
+
+fn add_points(raw: &mut Value) -> Option<()> {
+    fill_items(raw, "stories", "points", Value::Null)
+}
+
+fn add_labels(raw: &mut Value) -> Option<()> {
+    for table in ["epics", "stories"] {
+        fill_items(raw, table, "labels", json!([]))?;
+    }
+    Some(())
+}
+
+/// Items created before timestamps keep `null` ones rather than a made-up time.
+fn add_timestamps(raw: &mut Value) -> Option<()> {
+    for table in ["epics", "stories"] {
+        fill_items(raw, table, "created_at", Value::Null)?;
+        fill_items(raw, table, "updated_at", Value::Null)?;
+    }
+    Some(())
+}
+
+fn add_due_dates(raw: &mut Value) -> Option<()> {
+    for table in ["epics", "stories"] {
+        fill_items(raw, table, "due_date", Value::Null)?;
+    }
+    Some(())
+}
+
+fn add_comments(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "comments", json!({}))
+}
+
+fn add_history(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "history", json!([]))
+}
+
+fn add_journal(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "undo", json!([]))?;
+    fill_top(raw, "redo", json!([]))
+}
+
+fn add_trash(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "trash", json!({}))?;
+    fill_journal(raw, "trash", json!([]))
+}
+
+/// Statuses are stored by name either way; the bump only keeps older builds,
+/// which know just the four built-in states, from reading custom ones.
+fn allow_custom_statuses(_raw: &mut Value) -> Option<()> {
+    Some(())
+}
+
+fn add_subtasks(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "subtasks", json!({}))?;
+    fill_items(raw, "stories", "subtasks", json!([]))?;
+    fill_items(raw, "trash", "subtasks", json!({}))?;
+    fill_journal(raw, "subtasks", json!([]))
+}
+
+fn add_links(raw: &mut Value) -> Option<()> {
+    fill_top(raw, "links", json!({}))?;
+    fill_journal(raw, "links", json!([]))
+}
+
+/// Ids handed out so far all came from `last_item_id`, including those of
+/// trashed and undoable items, so each new sequence continues from it.
+fn split_id_sequences(raw: &mut Value) -> Option<()> {
+    let last_item_id = raw.get("last_item_id")?.clone();
+    for field in ["last_user_id", "last_comment_id", "last_link_id"] {
+        fill_top(raw, field, last_item_id.clone())?;
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DBState;
+
+    #[test]
+    fn migrate_should_upgrade_unversioned_file() {
+        let mut raw = serde_json::json!({
+            "last_item_id": 2,
+            "epics": { "1": { "id": 1, "name": "", "description": "", "status": "Open", "stories": [2] } },
+            "stories": { "2": { "id": 2, "name": "", "description": "", "status": "Open" } }
+        });
+
+        let applied = migrate(&mut raw).unwrap();
+
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(schema_version(&raw), CURRENT_SCHEMA_VERSION);
+        assert_eq!(raw["epics"]["1"].get("id"), None);
+        assert_eq!(raw["stories"]["2"].get("id"), None);
+        assert_eq!(raw["stories"]["2"]["priority"], "Major");
+        assert_eq!(raw["stories"]["2"]["subtasks"], json!([]));
+        assert_eq!(raw["links"], json!({}));
+        assert_eq!(raw["last_comment_id"], 2);
+        assert!(serde_json::from_value::<DBState>(raw).is_ok());
+    }
+
+    #[test]
+    fn migrate_should_fill_defaults_in_journal_and_trash() {
+        let mut raw = json!({
+            "schema_version": 10,
+            "last_item_id": 1,
+            "epics": {},
+            "stories": {},
+            "undo": [{ "at": "2024-01-01T00:00:00Z", "actor": null, "epics": [], "stories": [], "comments": [], "users": [] }],
+            "trash": { "1": { "deleted_at": "2024-01-01T00:00:00Z", "item": { "Epic": { "epic": { "name": "", "description": "", "status": "Closed", "stories": [] }, "stories": {} } }, "comments": {} } }
+        });
+
+        let applied = migrate(&mut raw).unwrap();
+
+        assert_eq!(applied.len(), 5);
+        assert_eq!(raw["undo"][0]["trash"], json!([]));
+        assert_eq!(raw["undo"][0]["links"], json!([]));
+        assert_eq!(raw["trash"]["1"]["subtasks"], json!({}));
+        assert!(serde_json::from_value::<DBState>(raw).is_ok());
+    }
+
+    #[test]
+    fn every_version_should_have_one_step() {
+        for (version, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, version as u32);
+        }
+        assert_eq!(MIGRATIONS.len() as u32, CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn migrate_should_skip_current_file() {
+        let mut raw = serde_json::json!({
+            "schema_version": CURRENT_SCHEMA_VERSION,
+            "last_item_id": 0,
+            "epics": {},
+            "stories": {}
+        });
+        let original = raw.clone();
+
+        assert!(migrate(&mut raw).unwrap().is_empty());
+        assert_eq!(raw, original);
+    }
+
+    #[test]
+    fn migrate_should_reject_newer_file() {
+        let mut raw = serde_json::json!({ "schema_version": CURRENT_SCHEMA_VERSION + 1 });
+        assert!(migrate(&mut raw).is_err());
+    }
+}
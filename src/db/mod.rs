@@ -0,0 +1,3289 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use error_stack::{IntoReport, Result, ResultExt};
+
+use crate::models::{
+    CloseEpicRule, Comment, DBState, Epic, EpicUpdate, Invariants, JournalEntry, LabelUpdate, Link,
+    LinkKind, Status, Story, StoryUpdate, SubTask, TrashEntry, TrashedItem, User, Workflow,
+    Workflows,
+};
+
+mod migrations;
+pub use migrations::CURRENT_SCHEMA_VERSION;
+
+mod history;
+mod journal;
+
+mod lock;
+pub use lock::DatabaseLock;
+use lock::DEFAULT_LOCK_TIMEOUT;
+
+mod sqlite;
+use sqlite::SQLiteDatabase;
+
+#[derive(Debug)]
+pub enum JiraDatabaseError {
+    Read,
+    Write,
+    Lock,
+    Conflict,
+    Migration,
+    Missing,
+    AlreadyExists,
+    NoEpicWithID,
+    NoStoryWithID,
+    NoSubTaskWithID,
+    NoUserWithID,
+    NoItemWithID,
+    NoCommentWithID,
+    NoLinkWithID,
+    NothingToUndo,
+    NothingToRedo,
+    NoTrashedItemWithID,
+    IllegalTransition,
+    InvariantViolation,
+    InvalidLink,
+    LinkCycle,
+    Blocked,
+    DuplicateUser,
+    InvalidLabel,
+    InvalidRetention,
+}
+
+impl std::fmt::Display for JiraDatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JiraDatabaseError::Read => {
+                write!(f, "Failed to read Jira database.")
+            }
+            JiraDatabaseError::Write => {
+                write!(f, "Failed to write Jira database.")
+            }
+            JiraDatabaseError::Lock => {
+                write!(
+                    f,
+                    "Jira database is locked by another session, try again later."
+                )
+            }
+            JiraDatabaseError::Conflict => {
+                write!(
+                    f,
+                    "Jira database was changed by another session since it was last read."
+                )
+            }
+            JiraDatabaseError::Migration => {
+                write!(f, "Failed to migrate Jira database.")
+            }
+            JiraDatabaseError::Missing => {
+                write!(f, "No Jira database found, run `init` to create one.")
+            }
+            JiraDatabaseError::AlreadyExists => {
+                write!(f, "Jira database already exists.")
+            }
+            JiraDatabaseError::NoEpicWithID => {
+                write!(f, "No Epic with ID found.")
+            }
+            JiraDatabaseError::NoStoryWithID => {
+                write!(f, "No Story with ID found.")
+            }
+            JiraDatabaseError::NoSubTaskWithID => {
+                write!(f, "No Sub-task with ID found.")
+            }
+            JiraDatabaseError::NoUserWithID => {
+                write!(f, "No User with ID found.")
+            }
+            JiraDatabaseError::NoItemWithID => {
+                write!(f, "No Epic or Story with ID found.")
+            }
+            JiraDatabaseError::NoCommentWithID => {
+                write!(f, "No Comment with ID found.")
+            }
+            JiraDatabaseError::NoLinkWithID => {
+                write!(f, "No Link with ID found.")
+            }
+            JiraDatabaseError::NothingToUndo => {
+                write!(f, "Nothing to undo.")
+            }
+            JiraDatabaseError::NothingToRedo => {
+                write!(f, "Nothing to redo.")
+            }
+            JiraDatabaseError::NoTrashedItemWithID => {
+                write!(f, "No Epic or Story with ID found in the trash.")
+            }
+            JiraDatabaseError::IllegalTransition => {
+                write!(f, "The workflow does not allow this status change.")
+            }
+            JiraDatabaseError::InvariantViolation => {
+                write!(
+                    f,
+                    "The change would leave an epic and its stories inconsistent."
+                )
+            }
+            JiraDatabaseError::InvalidLink => {
+                write!(f, "Stories can only be linked once, and not to themselves.")
+            }
+            JiraDatabaseError::LinkCycle => {
+                write!(f, "The link would make a story block itself.")
+            }
+            JiraDatabaseError::Blocked => {
+                write!(f, "The story is blocked by stories that are not done yet.")
+            }
+            JiraDatabaseError::DuplicateUser => {
+                write!(f, "A User with this username already exists.")
+            }
+            JiraDatabaseError::InvalidLabel => {
+                write!(
+                    f,
+                    "Labels must be non-empty and contain no spaces or commas."
+                )
+            }
+            JiraDatabaseError::InvalidRetention => {
+                write!(f, "Trash retention period is out of range.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for JiraDatabaseError {}
+
+/// How many writes are kept for undo, and how many undos for redo.
+pub const JOURNAL_LIMIT: usize = 50;
+/// Bytes of JSON kept on each of the undo and redo stacks. Entries copy the
+/// whole items they change, so a few large deletes can outweigh dozens of
+/// status changes; the oldest entries are dropped first.
+pub const JOURNAL_MAX_BYTES: usize = 1 << 20;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DatabaseBackend {
+    Json,
+    SQLite,
+}
+
+impl DatabaseBackend {
+    /// Picks the backend from the database file extension, defaulting to JSON.
+    pub fn from_path(file_path: &str) -> Self {
+        let extension = std::path::Path::new(file_path)
+            .extension()
+            .and_then(|extension| extension.to_str());
+        match extension {
+            Some("db" | "sqlite" | "sqlite3") => DatabaseBackend::SQLite,
+            _ => DatabaseBackend::Json,
+        }
+    }
+}
+
+impl std::str::FromStr for DatabaseBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(DatabaseBackend::Json),
+            "sqlite" => Ok(DatabaseBackend::SQLite),
+            _ => Err(format!("unknown backend '{}', expected json or sqlite", s)),
+        }
+    }
+}
+
+pub struct JiraDatabase {
+    pub database: Box<dyn Database>,
+    /// Revision of the state this session last read or wrote; writes based
+    /// on an older revision are refused with `JiraDatabaseError::Conflict`.
+    pub seen_revision: Cell<Option<u64>>,
+    /// Username recorded in the audit events of this session's writes.
+    pub actor: Option<String>,
+    /// Allowed status transitions of epics and stories.
+    pub workflows: Workflows,
+    pub invariants: Invariants,
+    /// State as read by `read_for_write`, diffed into audit events on write.
+    write_base: RefCell<Option<DBState>>,
+    /// Set inside `in_action`; `Some(true)` once the action has written, so
+    /// its later writes join that journal entry.
+    action: Cell<Option<bool>>,
+}
+
+impl JiraDatabase {
+    pub fn new(file_path: String) -> Self {
+        let backend = DatabaseBackend::from_path(&file_path);
+        Self::with_backend(file_path, backend)
+    }
+
+    pub fn with_backend(file_path: String, backend: DatabaseBackend) -> Self {
+        let database: Box<dyn Database> = match backend {
+            DatabaseBackend::Json => Box::new(JSONFileDatabase::new(file_path)),
+            DatabaseBackend::SQLite => Box::new(SQLiteDatabase::new(file_path)),
+        };
+        Self::with_database(database)
+    }
+
+    pub fn with_database(database: Box<dyn Database>) -> Self {
+        Self {
+            database,
+            seen_revision: Cell::new(None),
+            actor: None,
+            workflows: Workflows::default(),
+            invariants: Invariants::default(),
+            write_base: RefCell::new(None),
+            action: Cell::new(None),
+        }
+    }
+
+    pub fn with_actor(mut self, actor: Option<String>) -> Self {
+        self.actor = actor;
+        self
+    }
+
+    pub fn with_workflows(mut self, workflows: Workflows) -> Self {
+        self.workflows = workflows;
+        self
+    }
+
+    pub fn with_invariants(mut self, invariants: Invariants) -> Self {
+        self.invariants = invariants;
+        self
+    }
+
+    pub fn exists(&self) -> bool {
+        self.database.exists()
+    }
+
+    /// Creates a new database, empty or holding a sample project.
+    pub fn init(&self, sample: bool) -> Result<(), JiraDatabaseError> {
+        if self.exists() {
+            return Err(JiraDatabaseError::AlreadyExists).into_report();
+        }
+
+        let mut db_state = if sample {
+            DBState::sample()
+        } else {
+            DBState::new()
+        };
+        for epic in db_state.epics.values_mut() {
+            epic.status = self.workflows.epic.initial().clone();
+        }
+        for story in db_state.stories.values_mut() {
+            story.status = self.workflows.story.initial().clone();
+        }
+        self.database
+            .create(&db_state)
+            .change_context(JiraDatabaseError::Write)?;
+        self.seen_revision.set(Some(db_state.revision));
+        Ok(())
+    }
+
+    fn ensure_exists(&self) -> Result<(), JiraDatabaseError> {
+        if self.exists() {
+            Ok(())
+        } else {
+            Err(JiraDatabaseError::Missing).into_report()
+        }
+    }
+
+    pub fn recover(&self) -> Result<bool, JiraDatabaseError> {
+        self.database
+            .recover()
+            .change_context(JiraDatabaseError::Read)
+    }
+
+    pub fn migrate(&self, dry_run: bool) -> Result<Vec<&'static str>, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        self.database
+            .migrate(dry_run)
+            .change_context(JiraDatabaseError::Migration)
+    }
+
+    /// The items, without the history, journal and trash, which the SQLite
+    /// backend would otherwise load on every read; see `read_db_with`.
+    pub fn read_db(&self) -> Result<DBState, JiraDatabaseError> {
+        self.read_db_with(Sections::ITEMS)
+    }
+
+    pub fn read_db_with(&self, sections: Sections) -> Result<DBState, JiraDatabaseError> {
+        self.ensure_exists()?;
+        let db_state = self
+            .database
+            .read_sections(sections)
+            .change_context(JiraDatabaseError::Read)?;
+        self.seen_revision.set(Some(db_state.revision));
+        Ok(db_state)
+    }
+
+    /// States the epic, story or sub-task `item_id` may move to next. Unlike `read_db`,
+    /// this does not mark the current revision as seen, so a concurrent
+    /// change still surfaces as a conflict when the new status is written.
+    pub fn next_states(&self, item_id: u32) -> Result<Vec<Status>, JiraDatabaseError> {
+        let db_state = self
+            .database
+            .read_sections(Sections::ITEMS)
+            .change_context(JiraDatabaseError::Read)?;
+
+        if let Some(epic) = db_state.epics.get(&item_id) {
+            Ok(self.workflows.epic.next_states(&epic.status))
+        } else if let Some(story) = db_state.stories.get(&item_id) {
+            Ok(self.workflows.story.next_states(&story.status))
+        } else if let Some(subtask) = db_state.subtasks.get(&item_id) {
+            Ok(Workflow::default().next_states(&subtask.status))
+        } else {
+            Err(JiraDatabaseError::NoItemWithID).into_report()
+        }
+    }
+
+    fn read_for_write(&self) -> Result<DBState, JiraDatabaseError> {
+        let db_state = self
+            .database
+            .read_sections(Sections::WRITE)
+            .change_context(JiraDatabaseError::Read)?;
+
+        match self.seen_revision.get() {
+            Some(revision) if revision != db_state.revision => {
+                Err(JiraDatabaseError::Conflict).into_report()
+            }
+            _ => {
+                self.write_base.replace(Some(db_state.clone()));
+                Ok(db_state)
+            }
+        }
+    }
+
+    fn write_state(&self, db_state: &mut DBState) -> Result<(), JiraDatabaseError> {
+        self.commit(db_state, true)
+    }
+
+    /// Runs one user action, such as a CLI command, so that all of its
+    /// writes are undone and redone together; e.g. registering the current
+    /// user along with the epic they create.
+    pub fn in_action<T, C>(&self, action: impl FnOnce() -> Result<T, C>) -> Result<T, C> {
+        if self.action.get().is_some() {
+            return action();
+        }
+        self.action.set(Some(false));
+        let result = action();
+        self.action.set(None);
+        result
+    }
+
+    /// Writes `db_state`, recording the write for undo when `record_undo`;
+    /// undo and redo keep their own journal entries instead.
+    fn commit(&self, db_state: &mut DBState, record_undo: bool) -> Result<(), JiraDatabaseError> {
+        let before = self.write_base.take();
+        if let Some(before) = &before {
+            let events = history::diff(before, db_state, self.actor.as_deref(), Utc::now());
+            db_state.history.extend(events);
+            if record_undo {
+                if let Some(entry) = journal::diff(before, db_state, self.actor.as_deref()) {
+                    match db_state.undo.last_mut() {
+                        Some(last) if self.action.get() == Some(true) => {
+                            journal::merge(last, entry)
+                        }
+                        _ => push_journal_entry(&mut db_state.undo, entry),
+                    }
+                    if self.action.get().is_some() {
+                        self.action.set(Some(true));
+                    }
+                    db_state.redo.clear();
+                }
+            }
+        }
+        db_state.revision += 1;
+        self.database
+            .write_sections(db_state, before.as_ref(), Sections::WRITE)
+            .change_context(JiraDatabaseError::Write)?;
+        self.seen_revision.set(Some(db_state.revision));
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<DatabaseLock, JiraDatabaseError> {
+        self.ensure_exists()?;
+        self.database.lock().change_context(JiraDatabaseError::Lock)
+    }
+
+    /// Adds `epic` in the first state of the epic workflow.
+    pub fn create_epic(&self, mut epic: Epic) -> Result<u32, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        epic.status = self.workflows.epic.initial().clone();
+        let created_at = *epic.created_at.get_or_insert_with(Utc::now);
+        epic.updated_at = Some(created_at);
+
+        let id = db_state.last_item_id + 1;
+        db_state.epics.insert(id, epic);
+        db_state.last_item_id = id;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(id)
+    }
+
+    /// Adds `story` to `epic_id` in the first state of the story workflow.
+    pub fn create_story(&self, mut story: Story, epic_id: u32) -> Result<u32, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let epic = db_state
+            .epics
+            .get_mut(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        story.status = self.workflows.story.initial().clone();
+        self.check_closed_epic(epic_id, epic, &story.status)?;
+
+        let created_at = *story.created_at.get_or_insert_with(Utc::now);
+        story.updated_at = Some(created_at);
+        epic.updated_at = Some(Utc::now());
+
+        let id = db_state.last_item_id + 1;
+        db_state.stories.insert(id, story);
+        epic.stories.push(id);
+        db_state.last_item_id = id;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(id)
+    }
+
+    /// Adds a sub-task to the end of the checklist of `story_id`.
+    pub fn create_subtask(
+        &self,
+        mut subtask: SubTask,
+        story_id: u32,
+    ) -> Result<u32, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let story = db_state
+            .stories
+            .get_mut(&story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+
+        let now = Utc::now();
+        subtask.created_at = now;
+        subtask.updated_at = now;
+        story.updated_at = Some(now);
+
+        let id = db_state.last_item_id + 1;
+        db_state.subtasks.insert(id, subtask);
+        story.subtasks.push(id);
+        db_state.last_item_id = id;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(id)
+    }
+
+    pub fn delete_epic(&self, epic_id: u32) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let epic = db_state
+            .epics
+            .remove(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+
+        let stories = epic
+            .stories
+            .iter()
+            .filter_map(|story_id| {
+                db_state
+                    .stories
+                    .remove(story_id)
+                    .map(|story| (*story_id, story))
+            })
+            .collect::<HashMap<_, _>>();
+        let comments = take_comments(&mut db_state, |item_id| {
+            item_id == epic_id || stories.contains_key(&item_id)
+        });
+        let subtasks = take_subtasks(&mut db_state, stories.values());
+        db_state.trash.insert(
+            epic_id,
+            TrashEntry {
+                deleted_at: Utc::now(),
+                item: TrashedItem::Epic { epic, stories },
+                comments,
+                subtasks,
+            },
+        );
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn delete_story(&self, epic_id: u32, story_id: u32) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let epic = db_state
+            .epics
+            .get_mut(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        if epic.stories.contains(&story_id) {
+            let story = db_state
+                .stories
+                .remove(&story_id)
+                .ok_or(JiraDatabaseError::NoStoryWithID)?;
+            epic.stories.remove(
+                epic.stories
+                    .binary_search(&story_id)
+                    .expect("Story ID not in epic."),
+            );
+            epic.updated_at = Some(Utc::now());
+            let comments = take_comments(&mut db_state, |item_id| item_id == story_id);
+            let subtasks = take_subtasks(&mut db_state, [&story]);
+            db_state.trash.insert(
+                story_id,
+                TrashEntry {
+                    deleted_at: Utc::now(),
+                    item: TrashedItem::Story { epic_id, story },
+                    comments,
+                    subtasks,
+                },
+            );
+
+            self.write_state(&mut db_state)?;
+        } else {
+            return Err(JiraDatabaseError::NoStoryWithID).into_report();
+        }
+
+        Ok(())
+    }
+
+    /// Removes a sub-task from the checklist of `story_id`. Sub-tasks do not
+    /// go to the trash on their own; `undo` brings them back.
+    pub fn delete_subtask(&self, story_id: u32, subtask_id: u32) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let story = db_state
+            .stories
+            .get_mut(&story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+        let position = story
+            .subtasks
+            .iter()
+            .position(|id| *id == subtask_id)
+            .ok_or(JiraDatabaseError::NoSubTaskWithID)?;
+        story.subtasks.remove(position);
+        story.updated_at = Some(Utc::now());
+        db_state.subtasks.remove(&subtask_id);
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Puts a trashed epic, with its stories, or story back where it was
+    /// deleted from. A story's epic must not be in the trash itself.
+    pub fn restore(&self, item_id: u32) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let entry = db_state
+            .trash
+            .remove(&item_id)
+            .ok_or(JiraDatabaseError::NoTrashedItemWithID)?;
+        let now = Utc::now();
+        match entry.item {
+            TrashedItem::Epic { mut epic, stories } => {
+                epic.updated_at = Some(now);
+                db_state.epics.insert(item_id, epic);
+                db_state.stories.extend(stories);
+            }
+            TrashedItem::Story { epic_id, mut story } => {
+                let epic = db_state
+                    .epics
+                    .get_mut(&epic_id)
+                    .ok_or(JiraDatabaseError::NoEpicWithID)
+                    .into_report()
+                    .attach_printable("Restore the epic of the story first.")?;
+                let position = epic.stories.binary_search(&item_id).unwrap_or_else(|e| e);
+                epic.stories.insert(position, item_id);
+                epic.updated_at = Some(now);
+                story.updated_at = Some(now);
+                db_state.stories.insert(item_id, story);
+            }
+        }
+        db_state.comments.extend(entry.comments);
+        db_state.subtasks.extend(entry.subtasks);
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Deletes the trash entries older than `retention` for good and returns
+    /// how many there were.
+    pub fn purge_trash(&self, retention: Duration) -> Result<usize, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let cutoff = Some(retention)
+            .filter(|retention| *retention >= Duration::zero())
+            .and_then(|retention| Utc::now().checked_sub_signed(retention))
+            .ok_or(JiraDatabaseError::InvalidRetention)
+            .into_report()
+            .attach_printable_lazy(|| format!("Retention: {}", retention))?;
+        let count = db_state.trash.len();
+        db_state.trash.retain(|_, entry| entry.deleted_at >= cutoff);
+        let purged = count - db_state.trash.len();
+
+        let known = |id: &u32| db_state.stories.contains_key(id) || db_state.is_trashed(*id);
+        let dangling = db_state
+            .links
+            .iter()
+            .filter(|(_, link)| !known(&link.from) || !known(&link.to))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in dangling {
+            db_state.links.remove(&id);
+        }
+
+        if purged > 0 {
+            self.write_state(&mut db_state)?;
+        }
+
+        Ok(purged)
+    }
+
+    /// Re-parents a story, keeping its id.
+    pub fn move_story(
+        &self,
+        story_id: u32,
+        from_epic: u32,
+        to_epic: u32,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let to = db_state
+            .epics
+            .get(&to_epic)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        if from_epic != to_epic {
+            if let Some(story) = db_state.stories.get(&story_id) {
+                self.check_closed_epic(to_epic, to, &story.status)?;
+            }
+        }
+        let from = db_state
+            .epics
+            .get_mut(&from_epic)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        let position = from
+            .stories
+            .iter()
+            .position(|id| *id == story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+        if from_epic == to_epic {
+            return Ok(());
+        }
+        let now = Some(Utc::now());
+        from.stories.remove(position);
+        from.updated_at = now;
+
+        let to = db_state
+            .epics
+            .get_mut(&to_epic)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        // Stories are kept sorted, `delete_story` relies on it.
+        let position = to
+            .stories
+            .binary_search(&story_id)
+            .unwrap_or_else(|position| position);
+        to.stories.insert(position, story_id);
+        to.updated_at = now;
+        if let Some(story) = db_state.stories.get_mut(&story_id) {
+            story.updated_at = now;
+        }
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn update_epic_labels(
+        &self,
+        epic_id: u32,
+        update: LabelUpdate,
+    ) -> Result<(), JiraDatabaseError> {
+        if !update.is_valid() {
+            return Err(JiraDatabaseError::InvalidLabel).into_report();
+        }
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let epic = db_state
+            .epics
+            .get_mut(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+
+        update.apply(&mut epic.labels);
+        epic.updated_at = Some(Utc::now());
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn update_story_labels(
+        &self,
+        story_id: u32,
+        update: LabelUpdate,
+    ) -> Result<(), JiraDatabaseError> {
+        if !update.is_valid() {
+            return Err(JiraDatabaseError::InvalidLabel).into_report();
+        }
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let story = db_state
+            .stories
+            .get_mut(&story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+
+        update.apply(&mut story.labels);
+        story.updated_at = Some(Utc::now());
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Links two stories. A `Blocks` link must not close a loop of blockers.
+    pub fn add_link(&self, link: Link) -> Result<u32, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        if !db_state.stories.contains_key(&link.from) || !db_state.stories.contains_key(&link.to) {
+            return Err(JiraDatabaseError::NoStoryWithID).into_report();
+        }
+        let exists = db_state.links.values().any(|other| {
+            other.kind == link.kind
+                && ((other.from, other.to) == (link.from, link.to)
+                    || (link.kind != LinkKind::Blocks
+                        && (other.from, other.to) == (link.to, link.from)))
+        });
+        if link.from == link.to || exists {
+            return Err(JiraDatabaseError::InvalidLink).into_report();
+        }
+        if link.kind == LinkKind::Blocks {
+            if let Some(path) = blocking_path(&db_state, link.to, link.from) {
+                return Err(JiraDatabaseError::LinkCycle)
+                    .into_report()
+                    .attach_printable(format!(
+                        "{} already blocks {}: {}",
+                        link.to,
+                        link.from,
+                        path.iter()
+                            .map(u32::to_string)
+                            .collect::<Vec<_>>()
+                            .join(" -> ")
+                    ));
+            }
+        }
+
+        let now = Utc::now();
+        touch_item(&mut db_state, link.from, now)?;
+        touch_item(&mut db_state, link.to, now)?;
+
+        let id = db_state.last_link_id + 1;
+        db_state.links.insert(id, link);
+        db_state.last_link_id = id;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(id)
+    }
+
+    pub fn remove_link(&self, link_id: u32) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let link = db_state
+            .links
+            .remove(&link_id)
+            .ok_or(JiraDatabaseError::NoLinkWithID)?;
+        let now = Utc::now();
+        for story_id in [link.from, link.to] {
+            if let Some(story) = db_state.stories.get_mut(&story_id) {
+                story.updated_at = Some(now);
+            }
+        }
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Reverts the latest write of the current actor that has not been
+    /// undone yet, unless a later write changed the same items.
+    pub fn undo(&self) -> Result<JournalEntry, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let position = db_state
+            .undo
+            .iter()
+            .rposition(|entry| entry.actor == self.actor)
+            .ok_or(JiraDatabaseError::NothingToUndo)?;
+        let entry = db_state.undo.remove(position);
+        check_journal_conflicts(&entry, &db_state, false)?;
+        journal::revert(&entry, &mut db_state);
+        push_journal_entry(&mut db_state.redo, entry.clone());
+
+        self.commit(&mut db_state, false)?;
+        Ok(entry)
+    }
+
+    /// Repeats the latest write the current actor undid, until another write
+    /// clears the redo list.
+    pub fn redo(&self) -> Result<JournalEntry, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let position = db_state
+            .redo
+            .iter()
+            .rposition(|entry| entry.actor == self.actor)
+            .ok_or(JiraDatabaseError::NothingToRedo)?;
+        let entry = db_state.redo.remove(position);
+        check_journal_conflicts(&entry, &db_state, true)?;
+        journal::replay(&entry, &mut db_state);
+        push_journal_entry(&mut db_state.undo, entry.clone());
+
+        self.commit(&mut db_state, false)?;
+        Ok(entry)
+    }
+
+    /// Adds a comment on the epic or story `item_id` and returns its id.
+    pub fn add_comment(
+        &self,
+        item_id: u32,
+        author: Option<u32>,
+        body: String,
+    ) -> Result<u32, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let comment = Comment::new(item_id, author, body);
+        touch_item(&mut db_state, item_id, comment.created_at)?;
+
+        let id = db_state.last_comment_id + 1;
+        db_state.comments.insert(id, comment);
+        db_state.last_comment_id = id;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(id)
+    }
+
+    pub fn edit_comment(&self, comment_id: u32, body: String) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let now = Utc::now();
+        let comment = db_state
+            .comments
+            .get_mut(&comment_id)
+            .ok_or(JiraDatabaseError::NoCommentWithID)?;
+
+        comment.body = body;
+        comment.edited_at = Some(now);
+        let item_id = comment.item_id;
+        touch_item(&mut db_state, item_id, now)?;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn delete_comment(&self, comment_id: u32) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let comment = db_state
+            .comments
+            .remove(&comment_id)
+            .ok_or(JiraDatabaseError::NoCommentWithID)?;
+        touch_item(&mut db_state, comment.item_id, Utc::now())?;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn create_user(&self, user: User) -> Result<u32, JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        if db_state.find_user(&user.username).is_some() {
+            return Err(JiraDatabaseError::DuplicateUser).into_report();
+        }
+
+        let id = db_state.last_user_id + 1;
+        db_state.users.insert(id, user);
+        db_state.last_user_id = id;
+
+        self.write_state(&mut db_state)?;
+
+        Ok(id)
+    }
+
+    /// Id of the user with `username`, adding it to the directory if needed.
+    pub fn ensure_user(&self, username: &str) -> Result<u32, JiraDatabaseError> {
+        match self.read_db()?.find_user(username) {
+            Some(id) => Ok(id),
+            None => self.create_user(User::new(username.to_owned(), username.to_owned())),
+        }
+    }
+
+    /// Sets or, with `None`, clears the assignee of an epic.
+    pub fn assign_epic(
+        &self,
+        epic_id: u32,
+        assignee: Option<u32>,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        if assignee.is_some_and(|id| !db_state.users.contains_key(&id)) {
+            return Err(JiraDatabaseError::NoUserWithID).into_report();
+        }
+        let epic = db_state
+            .epics
+            .get_mut(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+
+        epic.assignee = assignee;
+        epic.updated_at = Some(Utc::now());
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Sets or, with `None`, clears the assignee of a story.
+    pub fn assign_story(
+        &self,
+        story_id: u32,
+        assignee: Option<u32>,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        if assignee.is_some_and(|id| !db_state.users.contains_key(&id)) {
+            return Err(JiraDatabaseError::NoUserWithID).into_report();
+        }
+        let story = db_state
+            .stories
+            .get_mut(&story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+
+        story.assignee = assignee;
+        story.updated_at = Some(Utc::now());
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Finishing an epic whose stories are not all done follows
+    /// `Invariants::close_epic`.
+    pub fn update_epic_status(
+        &self,
+        epic_id: u32,
+        status: Status,
+    ) -> Result<(), JiraDatabaseError> {
+        let cascade = self.invariants.close_epic == CloseEpicRule::Cascade;
+        self.set_epic_status(epic_id, status, cascade)
+    }
+
+    /// Like `update_epic_status`, but always moves the stories that are not
+    /// done to the epic's new status when it finishes the epic.
+    pub fn update_epic_status_cascading(
+        &self,
+        epic_id: u32,
+        status: Status,
+    ) -> Result<(), JiraDatabaseError> {
+        self.set_epic_status(epic_id, status, true)
+    }
+
+    fn set_epic_status(
+        &self,
+        epic_id: u32,
+        status: Status,
+        cascade: bool,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let epic = db_state
+            .epics
+            .get(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        check_transition(&self.workflows.epic, &epic.status, &status)?;
+
+        let now = Utc::now();
+        if self.workflows.epic.is_done(&status) {
+            let unfinished = epic
+                .stories
+                .iter()
+                .copied()
+                .filter(|story_id| {
+                    db_state
+                        .stories
+                        .get(story_id)
+                        .is_some_and(|story| !self.workflows.story.is_done(&story.status))
+                })
+                .collect::<Vec<_>>();
+            if cascade {
+                for story_id in unfinished {
+                    let story = db_state
+                        .stories
+                        .get_mut(&story_id)
+                        .expect("Story ID not in stories.");
+                    check_transition(&self.workflows.story, &story.status, &status)?;
+                    story.status = status.clone();
+                    story.updated_at = Some(now);
+                }
+                // Stories of the epic may block each other, so check once
+                // all of them are done.
+                for story_id in &epic.stories {
+                    check_blockers(&db_state, &self.workflows.story, *story_id)?;
+                }
+            } else if self.invariants.close_epic == CloseEpicRule::Block && !unfinished.is_empty() {
+                return Err(JiraDatabaseError::InvariantViolation)
+                    .into_report()
+                    .attach_printable(format!(
+                        "Stories not done yet: {}",
+                        unfinished
+                            .iter()
+                            .map(u32::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+            }
+        }
+
+        let epic = db_state
+            .epics
+            .get_mut(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+        epic.status = status;
+        epic.updated_at = Some(now);
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Under the block rule, a done epic may only hold stories that are done
+    /// too.
+    fn check_closed_epic(
+        &self,
+        epic_id: u32,
+        epic: &Epic,
+        story_status: &Status,
+    ) -> Result<(), JiraDatabaseError> {
+        if self.invariants.close_epic == CloseEpicRule::Block
+            && self.workflows.epic.is_done(&epic.status)
+            && !self.workflows.story.is_done(story_status)
+        {
+            Err(JiraDatabaseError::InvariantViolation)
+                .into_report()
+                .attach_printable(format!(
+                    "Epic {} is {}, its stories must be done",
+                    epic_id, epic.status
+                ))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn update_story_status(
+        &self,
+        story_id: u32,
+        status: Status,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let story = db_state
+            .stories
+            .get(&story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+
+        check_transition(&self.workflows.story, &story.status, &status)?;
+        if self.workflows.story.is_done(&status) {
+            check_blockers(&db_state, &self.workflows.story, story_id)?;
+        }
+        if let Some((epic_id, epic)) = db_state
+            .epics
+            .iter()
+            .find(|(_, epic)| epic.stories.contains(&story_id))
+        {
+            self.check_closed_epic(*epic_id, epic, &status)?;
+        }
+        let story = db_state
+            .stories
+            .get_mut(&story_id)
+            .expect("Story ID not in stories.");
+        // A story is under way in any state but the initial and done ones.
+        let starts =
+            status != *self.workflows.story.initial() && !self.workflows.story.is_done(&status);
+        story.status = status;
+        story.updated_at = Some(Utc::now());
+
+        if starts && self.invariants.start_epic_with_story {
+            let epic = db_state
+                .epics
+                .values_mut()
+                .find(|epic| epic.stories.contains(&story_id))
+                .filter(|epic| epic.status == *self.workflows.epic.initial());
+            if let Some((epic, started)) = epic.zip(self.workflows.epic.started()) {
+                epic.status = started;
+                epic.updated_at = Some(Utc::now());
+            }
+        }
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    /// Sub-tasks follow the default workflow.
+    pub fn update_subtask_status(
+        &self,
+        subtask_id: u32,
+        status: Status,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let subtask = db_state
+            .subtasks
+            .get_mut(&subtask_id)
+            .ok_or(JiraDatabaseError::NoSubTaskWithID)?;
+
+        check_transition(&Workflow::default(), &subtask.status, &status)?;
+        subtask.status = status;
+        subtask.updated_at = Utc::now();
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn update_epic(&self, epic_id: u32, update: EpicUpdate) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let epic = db_state
+            .epics
+            .get_mut(&epic_id)
+            .ok_or(JiraDatabaseError::NoEpicWithID)?;
+
+        update.apply(epic);
+        epic.updated_at = Some(Utc::now());
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+
+    pub fn update_story(
+        &self,
+        story_id: u32,
+        update: StoryUpdate,
+    ) -> Result<(), JiraDatabaseError> {
+        let _lock = self.lock()?;
+        let mut db_state = self.read_for_write()?;
+
+        let story = db_state
+            .stories
+            .get_mut(&story_id)
+            .ok_or(JiraDatabaseError::NoStoryWithID)?;
+
+        update.apply(story);
+        story.updated_at = Some(Utc::now());
+
+        self.write_state(&mut db_state)?;
+
+        Ok(())
+    }
+}
+
+fn check_transition(
+    workflow: &Workflow,
+    from: &Status,
+    to: &Status,
+) -> Result<(), JiraDatabaseError> {
+    if workflow.allows(from, to) {
+        Ok(())
+    } else {
+        Err(JiraDatabaseError::IllegalTransition)
+            .into_report()
+            .attach_printable(format!("{} -> {}", from, to))
+    }
+}
+
+/// Undoing or redoing `entry` must not overwrite later writes.
+fn check_journal_conflicts(
+    entry: &JournalEntry,
+    db_state: &DBState,
+    forward: bool,
+) -> Result<(), JiraDatabaseError> {
+    let conflicts = journal::conflicts(entry, db_state, forward);
+    if conflicts.is_empty() {
+        Ok(())
+    } else {
+        Err(JiraDatabaseError::Conflict)
+            .into_report()
+            .attach_printable(format!(
+                "Changed since: {}",
+                conflicts
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+    }
+}
+
+fn check_blockers(
+    db_state: &DBState,
+    workflow: &Workflow,
+    story_id: u32,
+) -> Result<(), JiraDatabaseError> {
+    let blockers = db_state.open_blockers(story_id, workflow);
+    if blockers.is_empty() {
+        Ok(())
+    } else {
+        Err(JiraDatabaseError::Blocked)
+            .into_report()
+            .attach_printable(format!(
+                "Story {} is blocked by: {}",
+                story_id,
+                blockers
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+    }
+}
+
+/// Stories from `from` to `to` along `Blocks` links, if `from` blocks `to`
+/// directly or through other stories.
+fn blocking_path(db_state: &DBState, from: u32, to: u32) -> Option<Vec<u32>> {
+    // Breadth-first, remembering the story each one was reached from.
+    let mut reached_from = HashMap::new();
+    let mut queue = std::collections::VecDeque::from([from]);
+    while let Some(id) = queue.pop_front() {
+        if id == to {
+            let mut path = vec![to];
+            let mut step = to;
+            while step != from {
+                step = reached_from[&step];
+                path.push(step);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for link in db_state.links.values() {
+            if link.kind == LinkKind::Blocks
+                && link.from == id
+                && link.to != from
+                && !reached_from.contains_key(&link.to)
+            {
+                reached_from.insert(link.to, id);
+                queue.push_back(link.to);
+            }
+        }
+    }
+    None
+}
+
+/// Removes and returns the comments on the items matching `on_item`.
+fn take_comments(db_state: &mut DBState, on_item: impl Fn(u32) -> bool) -> HashMap<u32, Comment> {
+    let ids = db_state
+        .comments
+        .iter()
+        .filter(|(_, comment)| on_item(comment.item_id))
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    ids.into_iter()
+        .filter_map(|id| db_state.comments.remove(&id).map(|comment| (id, comment)))
+        .collect()
+}
+
+/// Removes and returns the sub-tasks of `stories`.
+fn take_subtasks<'a>(
+    db_state: &mut DBState,
+    stories: impl IntoIterator<Item = &'a Story>,
+) -> HashMap<u32, SubTask> {
+    stories
+        .into_iter()
+        .flat_map(|story| story.subtasks.iter())
+        .filter_map(|id| db_state.subtasks.remove(id).map(|subtask| (*id, subtask)))
+        .collect()
+}
+
+/// Pushes `entry`, dropping the oldest entries beyond `JOURNAL_LIMIT` or
+/// `JOURNAL_MAX_BYTES`. The latest entry is kept however large it is.
+fn push_journal_entry(entries: &mut Vec<JournalEntry>, entry: JournalEntry) {
+    entries.push(entry);
+    let mut bytes = 0;
+    let kept = entries
+        .iter()
+        .rev()
+        .take(JOURNAL_LIMIT)
+        .take_while(|entry| {
+            bytes += serde_json::to_vec(entry).map_or(0, |json| json.len());
+            bytes <= JOURNAL_MAX_BYTES
+        })
+        .count()
+        .max(1);
+    entries.drain(..entries.len() - kept);
+}
+
+/// Sets `updated_at` of the epic or story `item_id`.
+fn touch_item(
+    db_state: &mut DBState,
+    item_id: u32,
+    now: DateTime<Utc>,
+) -> Result<(), JiraDatabaseError> {
+    if let Some(epic) = db_state.epics.get_mut(&item_id) {
+        epic.updated_at = Some(now);
+    } else if let Some(story) = db_state.stories.get_mut(&item_id) {
+        story.updated_at = Some(now);
+    } else {
+        return Err(JiraDatabaseError::NoItemWithID).into_report();
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum DatabaseError {
+    ReadError,
+    WriteError,
+    LockError,
+    MigrationError,
+}
+
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::ReadError => {
+                write!(f, "Failed to read database.")
+            }
+            DatabaseError::WriteError => {
+                write!(f, "Failed to write database.")
+            }
+            DatabaseError::LockError => {
+                write!(f, "Failed to lock database.")
+            }
+            DatabaseError::MigrationError => {
+                write!(f, "Failed to migrate database.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+/// Parts of `DBState` that only some reads need. Backends may leave out the
+/// ones not asked for; the JSON file is always read whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sections {
+    pub history: bool,
+    /// The undo and redo stacks.
+    pub journal: bool,
+    pub trash: bool,
+}
+
+impl Sections {
+    /// Epics, stories and what is attached to them.
+    pub const ITEMS: Self = Self {
+        history: false,
+        journal: false,
+        trash: false,
+    };
+    pub const HISTORY: Self = Self {
+        history: true,
+        ..Self::ITEMS
+    };
+    pub const TRASH: Self = Self {
+        trash: true,
+        ..Self::ITEMS
+    };
+    pub const ALL: Self = Self {
+        history: true,
+        journal: true,
+        trash: true,
+    };
+    /// What a write needs for its journal entry and history events, which
+    /// are appended without reading the history.
+    const WRITE: Self = Self {
+        history: false,
+        journal: true,
+        trash: true,
+    };
+}
+
+pub trait Database {
+    fn read_db(&self) -> Result<DBState, DatabaseError>;
+    fn write_db(&self, db_state: &DBState) -> Result<(), DatabaseError>;
+
+    /// Like `read_db`, but may leave the sections not in `sections` empty.
+    fn read_sections(&self, _sections: Sections) -> Result<DBState, DatabaseError> {
+        self.read_db()
+    }
+
+    /// Writes a state read with `read_sections(sections)`. Without the
+    /// history section, its history holds only new events to append.
+    /// `previous` is the state as it was read, if at hand, so that only
+    /// what changed since needs writing.
+    fn write_sections(
+        &self,
+        db_state: &DBState,
+        _previous: Option<&DBState>,
+        _sections: Sections,
+    ) -> Result<(), DatabaseError> {
+        self.write_db(db_state)
+    }
+
+    fn exists(&self) -> bool {
+        true
+    }
+
+    /// Writes the first state of a new database, creating missing parent
+    /// directories.
+    fn create(&self, db_state: &DBState) -> Result<(), DatabaseError> {
+        self.write_db(db_state)
+    }
+
+    /// Restores the database from a backup if it can no longer be read.
+    /// Returns whether a recovery took place.
+    fn recover(&self) -> Result<bool, DatabaseError> {
+        Ok(false)
+    }
+
+    /// Takes an exclusive lock guarding a read-modify-write cycle; it is
+    /// released when the returned guard is dropped.
+    fn lock(&self) -> Result<DatabaseLock, DatabaseError> {
+        Ok(DatabaseLock::none())
+    }
+
+    /// Upgrades the stored data to `CURRENT_SCHEMA_VERSION`, returning the
+    /// steps applied. With `dry_run` the steps are only reported.
+    fn migrate(&self, _dry_run: bool) -> Result<Vec<&'static str>, DatabaseError> {
+        Ok(vec![])
+    }
+}
+
+fn create_parent_dir(file_path: &str) -> Result<(), DatabaseError> {
+    match std::path::Path::new(file_path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent)
+            .into_report()
+            .change_context(DatabaseError::WriteError),
+        _ => Ok(()),
+    }
+}
+
+/// Number of previous good copies of the JSON database kept next to it.
+const DEFAULT_BACKUP_COUNT: usize = 3;
+
+struct JSONFileDatabase {
+    pub file_path: String,
+    pub backup_count: usize,
+    pub lock_timeout: std::time::Duration,
+}
+
+impl JSONFileDatabase {
+    fn new(file_path: String) -> Self {
+        Self {
+            file_path,
+            backup_count: DEFAULT_BACKUP_COUNT,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+
+    fn backup_path(&self, index: usize) -> String {
+        format!("{}.{}", self.file_path, index)
+    }
+
+    fn parse_raw(path: &str) -> Result<serde_json::Value, DatabaseError> {
+        let raw_content = std::fs::read_to_string(path)
+            .into_report()
+            .change_context(DatabaseError::ReadError)?;
+
+        serde_json::from_str::<serde_json::Value>(&raw_content)
+            .into_report()
+            .change_context(DatabaseError::ReadError)
+    }
+
+    /// Reads `path`, upgrading it in memory if it uses an older schema.
+    fn parse_file(path: &str) -> Result<DBState, DatabaseError> {
+        let mut raw = Self::parse_raw(path)?;
+        migrations::migrate(&mut raw).change_context(DatabaseError::ReadError)?;
+
+        serde_json::from_value::<DBState>(raw)
+            .into_report()
+            .change_context(DatabaseError::ReadError)
+    }
+
+    /// Shifts `db.json.1..N-1` up by one and moves the current file to
+    /// `db.json.1`, but only if the current file is still readable.
+    fn rotate_backups(&self) -> Result<(), DatabaseError> {
+        if self.backup_count == 0 || Self::parse_file(&self.file_path).is_err() {
+            return Ok(());
+        }
+
+        for index in (1..self.backup_count).rev() {
+            let from = self.backup_path(index);
+            if std::path::Path::new(&from).exists() {
+                std::fs::rename(&from, self.backup_path(index + 1))
+                    .into_report()
+                    .change_context(DatabaseError::WriteError)?;
+            }
+        }
+
+        std::fs::copy(&self.file_path, self.backup_path(1))
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+
+        Ok(())
+    }
+
+    /// Writes `contents` to a sibling temp file, fsyncs it and renames it over
+    /// `path`, so readers only ever see the old or the new file.
+    fn write_atomically(path: &str, contents: &[u8]) -> Result<(), DatabaseError> {
+        use std::io::Write;
+
+        let tmp_path = format!("{}.tmp", path);
+        let mut file = std::fs::File::create(&tmp_path)
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+        file.write_all(contents)
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+        file.sync_all()
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+        drop(file);
+
+        std::fs::rename(&tmp_path, path)
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+
+        #[cfg(unix)]
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            let parent = if parent.as_os_str().is_empty() {
+                std::path::Path::new(".")
+            } else {
+                parent
+            };
+            if let Ok(dir) = std::fs::File::open(parent) {
+                let _ = dir.sync_all();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Database for JSONFileDatabase {
+    fn read_db(&self) -> Result<DBState, DatabaseError> {
+        Self::parse_file(&self.file_path)
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<(), DatabaseError> {
+        let contents = serde_json::to_vec(db_state)
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+
+        self.rotate_backups()?;
+        Self::write_atomically(&self.file_path, &contents)
+    }
+
+    fn recover(&self) -> Result<bool, DatabaseError> {
+        if !std::path::Path::new(&self.file_path).exists()
+            || Self::parse_file(&self.file_path).is_ok()
+        {
+            return Ok(false);
+        }
+
+        for index in 1..=self.backup_count {
+            let backup_path = self.backup_path(index);
+            if Self::parse_file(&backup_path).is_ok() {
+                let contents = std::fs::read(&backup_path)
+                    .into_report()
+                    .change_context(DatabaseError::ReadError)?;
+                Self::write_atomically(&self.file_path, &contents)?;
+                return Ok(true);
+            }
+        }
+
+        Err(DatabaseError::ReadError).into_report()
+    }
+
+    fn lock(&self) -> Result<DatabaseLock, DatabaseError> {
+        DatabaseLock::acquire(&self.file_path, self.lock_timeout)
+    }
+
+    fn exists(&self) -> bool {
+        std::path::Path::new(&self.file_path).exists()
+    }
+
+    fn create(&self, db_state: &DBState) -> Result<(), DatabaseError> {
+        create_parent_dir(&self.file_path)?;
+        self.write_db(db_state)
+    }
+
+    fn migrate(&self, dry_run: bool) -> Result<Vec<&'static str>, DatabaseError> {
+        let mut raw = Self::parse_raw(&self.file_path)?;
+        let version = migrations::schema_version(&raw);
+
+        let applied = migrations::migrate(&mut raw)?;
+        let db_state = serde_json::from_value::<DBState>(raw)
+            .into_report()
+            .change_context(DatabaseError::MigrationError)?;
+        if applied.is_empty() || dry_run {
+            return Ok(applied);
+        }
+
+        std::fs::copy(
+            &self.file_path,
+            format!("{}.v{}.bak", self.file_path, version),
+        )
+        .into_report()
+        .change_context(DatabaseError::WriteError)?;
+
+        let contents = serde_json::to_vec(&db_state)
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+        Self::write_atomically(&self.file_path, &contents)?;
+
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+pub mod test_utils {
+    use std::{cell::RefCell, collections::HashMap};
+
+    use super::*;
+
+    pub struct MockDB {
+        last_written_state: RefCell<DBState>,
+    }
+
+    impl MockDB {
+        pub fn new() -> Self {
+            Self {
+                last_written_state: RefCell::new(DBState {
+                    schema_version: CURRENT_SCHEMA_VERSION,
+                    last_item_id: 0,
+                    last_user_id: 0,
+                    last_comment_id: 0,
+                    last_link_id: 0,
+                    revision: 0,
+                    epics: HashMap::new(),
+                    stories: HashMap::new(),
+                    subtasks: HashMap::new(),
+                    users: HashMap::new(),
+                    comments: HashMap::new(),
+                    links: HashMap::new(),
+                    history: Vec::new(),
+                    undo: Vec::new(),
+                    redo: Vec::new(),
+                    trash: HashMap::new(),
+                }),
+            }
+        }
+    }
+
+    impl Database for MockDB {
+        fn read_db(&self) -> Result<DBState, DatabaseError> {
+            // TODO: fix this error by deriving the appropriate traits for Story
+            let state = self.last_written_state.borrow().clone();
+            Ok(state)
+        }
+
+        fn write_db(&self, db_state: &DBState) -> Result<(), DatabaseError> {
+            let latest_state = &self.last_written_state;
+            // TODO: fix this error by deriving the appropriate traits for DBState
+            *latest_state.borrow_mut() = db_state.clone();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_utils::MockDB;
+    use super::*;
+    use crate::models::Priority;
+
+    #[test]
+    fn create_epic_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+
+        // TODO: fix this error by deriving the appropriate traits for Epic
+        let result = db.create_epic(epic.clone());
+
+        assert_eq!(result.is_ok(), true);
+
+        let id = result.unwrap();
+        let db_state = db.read_db().unwrap();
+
+        let expected_id = 1;
+
+        assert_eq!(id, expected_id);
+        assert_eq!(db_state.last_item_id, expected_id);
+        assert_eq!(db_state.epics.get(&id), Some(&epic));
+    }
+
+    #[test]
+    fn create_story_should_error_if_invalid_epic_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let non_existent_epic_id = 999;
+
+        let result = db.create_story(story, non_existent_epic_id);
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn create_story_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+        assert_eq!(result.is_ok(), true);
+
+        let epic_id = result.unwrap();
+
+        // TODO: fix this error by deriving the appropriate traits for Story
+        let result = db.create_story(story.clone(), epic_id);
+        assert_eq!(result.is_ok(), true);
+
+        let id = result.unwrap();
+        let db_state = db.read_db().unwrap();
+
+        let expected_id = 2;
+
+        assert_eq!(id, expected_id);
+        assert_eq!(db_state.last_item_id, expected_id);
+        assert_eq!(
+            db_state.epics.get(&epic_id).unwrap().stories.contains(&id),
+            true
+        );
+        assert_eq!(db_state.stories.get(&id), Some(&story));
+    }
+
+    #[test]
+    fn delete_epic_should_error_if_invalid_epic_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let non_existent_epic_id = 999;
+
+        let result = db.delete_epic(non_existent_epic_id);
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn delete_epic_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+        assert_eq!(result.is_ok(), true);
+
+        let epic_id = result.unwrap();
+
+        let result = db.create_story(story, epic_id);
+        assert_eq!(result.is_ok(), true);
+
+        let story_id = result.unwrap();
+
+        let result = db.delete_epic(epic_id);
+        assert_eq!(result.is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+
+        let expected_last_id = 2;
+
+        assert_eq!(db_state.last_item_id, expected_last_id);
+        assert_eq!(db_state.epics.get(&epic_id), None);
+        assert_eq!(db_state.stories.get(&story_id), None);
+    }
+
+    #[test]
+    fn delete_story_should_error_if_invalid_epic_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+        assert_eq!(result.is_ok(), true);
+
+        let epic_id = result.unwrap();
+
+        let result = db.create_story(story, epic_id);
+        assert_eq!(result.is_ok(), true);
+
+        let story_id = result.unwrap();
+
+        let non_existent_epic_id = 999;
+
+        let result = db.delete_story(non_existent_epic_id, story_id);
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn delete_story_should_error_if_story_not_found_in_epic() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+        assert_eq!(result.is_ok(), true);
+
+        let epic_id = result.unwrap();
+
+        let result = db.create_story(story, epic_id);
+        assert_eq!(result.is_ok(), true);
+
+        let non_existent_story_id = 999;
+
+        let result = db.delete_story(epic_id, non_existent_story_id);
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn delete_story_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+        assert_eq!(result.is_ok(), true);
+
+        let epic_id = result.unwrap();
+
+        let result = db.create_story(story, epic_id);
+        assert_eq!(result.is_ok(), true);
+
+        let story_id = result.unwrap();
+
+        let result = db.delete_story(epic_id, story_id);
+        assert_eq!(result.is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+
+        let expected_last_id = 2;
+
+        assert_eq!(db_state.last_item_id, expected_last_id);
+        assert_eq!(
+            db_state
+                .epics
+                .get(&epic_id)
+                .unwrap()
+                .stories
+                .contains(&story_id),
+            false
+        );
+        assert_eq!(db_state.stories.get(&story_id), None);
+    }
+
+    #[test]
+    fn move_story_should_error_if_story_not_in_epic() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let from_epic = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let to_epic = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), to_epic)
+            .unwrap();
+
+        assert!(db.move_story(story_id, from_epic, to_epic).is_err());
+        assert!(db.move_story(story_id, to_epic, 999).is_err());
+    }
+
+    #[test]
+    fn move_story_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let from_epic = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), from_epic)
+            .unwrap();
+        let to_epic = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let later_story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), to_epic)
+            .unwrap();
+
+        db.move_story(story_id, from_epic, to_epic).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics[&from_epic].stories.is_empty());
+        assert_eq!(
+            db_state.epics[&to_epic].stories,
+            vec![story_id, later_story_id]
+        );
+        assert!(db_state.stories.contains_key(&story_id));
+
+        // Still deletable from its new epic.
+        db.delete_story(to_epic, story_id).unwrap();
+    }
+
+    #[test]
+    fn update_story_labels_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        db.update_story_labels(story_id, LabelUpdate::parse("backend +tech-debt"))
+            .unwrap();
+        db.update_story_labels(story_id, LabelUpdate::parse("-backend customer-x"))
+            .unwrap();
+        assert!(db
+            .update_story_labels(story_id, LabelUpdate::parse("a,b"))
+            .is_err());
+        assert!(db
+            .update_epic_labels(999, LabelUpdate::parse("backend"))
+            .is_err());
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(
+            db_state.stories[&story_id]
+                .labels
+                .iter()
+                .collect::<Vec<_>>(),
+            vec!["customer-x", "tech-debt"]
+        );
+    }
+
+    #[test]
+    fn mutators_should_maintain_timestamps() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        // Created a day ago, so later writes are strictly newer even on a
+        // coarse clock.
+        let past = Some(Utc::now() - chrono::Duration::days(1));
+        let mut epic = Epic::new("".to_owned(), "".to_owned());
+        epic.created_at = past;
+        let epic_id = db.create_epic(epic).unwrap();
+
+        let created = db.read_db().unwrap().epics[&epic_id].clone();
+        assert_eq!(created.created_at, past);
+        assert_eq!(created.updated_at, created.created_at);
+
+        let mut story = Story::new("".to_owned(), "".to_owned());
+        story.created_at = past;
+        let story_id = db.create_story(story, epic_id).unwrap();
+        db.update_story(
+            story_id,
+            StoryUpdate {
+                due_date: Some(chrono::NaiveDate::from_ymd_opt(2024, 2, 29)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let epic = &db_state.epics[&epic_id];
+        let story = &db_state.stories[&story_id];
+        assert_eq!(epic.created_at, created.created_at);
+        assert!(epic.updated_at > created.updated_at);
+        assert!(story.updated_at > story.created_at);
+        let workflow = Workflow::default();
+        assert!(story.is_overdue(
+            &workflow,
+            chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        ));
+        assert!(!story.is_overdue(
+            &workflow,
+            chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        ));
+    }
+
+    #[test]
+    fn comments_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let first = db.add_comment(story_id, None, "first".to_owned()).unwrap();
+        let second = db.add_comment(story_id, None, "second".to_owned()).unwrap();
+        db.add_comment(epic_id, None, "on epic".to_owned()).unwrap();
+        assert!(db.add_comment(999, None, "".to_owned()).is_err());
+
+        db.edit_comment(first, "first, edited".to_owned()).unwrap();
+        db.delete_comment(second).unwrap();
+        assert!(db.delete_comment(second).is_err());
+        assert!(db.edit_comment(999, "".to_owned()).is_err());
+
+        let db_state = db.read_db().unwrap();
+        let comments = db_state.comments_on(story_id);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].0, first);
+        assert_eq!(comments[0].1.body, "first, edited");
+        assert!(comments[0].1.edited_at.is_some());
+
+        db.delete_epic(epic_id).unwrap();
+        assert!(db.read_db().unwrap().comments.is_empty());
+    }
+
+    #[test]
+    fn mutators_should_append_audit_events() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()))
+            .with_actor(Some("jdoe".to_owned()));
+        let epic_id = db
+            .create_epic(Epic::new("epic".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("story".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        db.update_story_status(story_id, Status::Closed).unwrap();
+        db.delete_story(epic_id, story_id).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let story_fields = db_state
+            .history_of(story_id)
+            .iter()
+            .map(|event| event.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(story_fields, vec!["created", "status", "deleted"]);
+
+        let epic_history = db_state.history_of(epic_id);
+        assert_eq!(epic_history[0].field, "created");
+        assert_eq!(epic_history[0].new, "epic");
+        assert_eq!(epic_history[0].actor.as_deref(), Some("jdoe"));
+        assert_eq!(epic_history.len(), 3);
+    }
+
+    #[test]
+    fn restore_should_undo_deletes() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let other_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        db.add_comment(story_id, None, "comment".to_owned())
+            .unwrap();
+
+        db.delete_story(epic_id, story_id).unwrap();
+        db.delete_epic(epic_id).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics.is_empty());
+        assert!(db_state.stories.is_empty());
+        assert!(db_state.comments.is_empty());
+        assert_eq!(db_state.trash.len(), 2);
+
+        // The story goes back into its epic, which must be restored first.
+        assert!(db.restore(story_id).is_err());
+        db.restore(epic_id).unwrap();
+        db.restore(story_id).unwrap();
+        assert!(db.restore(story_id).is_err());
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.trash.is_empty());
+        assert_eq!(db_state.epics[&epic_id].stories, vec![story_id, other_id]);
+        assert_eq!(db_state.comments_on(story_id).len(), 1);
+        assert_eq!(
+            db_state.history_of(story_id).last().unwrap().field,
+            "restored"
+        );
+    }
+
+    #[test]
+    fn purge_trash_should_keep_recent_items() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        db.delete_epic(epic_id).unwrap();
+
+        assert_eq!(db.purge_trash(Duration::days(30)).unwrap(), 0);
+        assert_eq!(db.purge_trash(Duration::zero()).unwrap(), 1);
+        assert!(db.read_db().unwrap().trash.is_empty());
+    }
+
+    #[test]
+    fn purge_trash_should_reject_retention_out_of_range() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        db.delete_epic(epic_id).unwrap();
+
+        for retention in [Duration::days(-1), Duration::days(u32::MAX.into())] {
+            assert!(matches!(
+                db.purge_trash(retention).unwrap_err().current_context(),
+                JiraDatabaseError::InvalidRetention
+            ));
+        }
+        assert_eq!(db.read_db().unwrap().trash.len(), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let created = db.read_db().unwrap();
+
+        db.delete_epic(epic_id).unwrap();
+        db.undo().unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics, created.epics);
+        assert_eq!(db_state.stories, created.stories);
+
+        db.redo().unwrap();
+        assert!(db.read_db().unwrap().epics.is_empty());
+        assert!(db.redo().is_err());
+
+        db.undo().unwrap();
+        db.undo().unwrap();
+        assert!(!db.read_db().unwrap().stories.contains_key(&story_id));
+
+        // A new write drops the undone ones.
+        db.update_epic_status(epic_id, Status::Closed).unwrap();
+        assert!(db.redo().is_err());
+    }
+
+    #[test]
+    fn in_action_should_undo_all_writes_together() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        db.in_action(|| {
+            let reporter = db.ensure_user("jdoe")?;
+            let mut epic = Epic::new("".to_owned(), "".to_owned());
+            epic.reporter = Some(reporter);
+            db.create_epic(epic)
+        })
+        .unwrap();
+        db.create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.undo.len(), 2);
+        assert_eq!(db_state.undo[0].users.len(), 1);
+        assert_eq!(db_state.undo[0].epics.len(), 1);
+
+        db.undo().unwrap();
+        let entry = db.undo().unwrap();
+        assert_eq!(entry.item_ids(), vec![1]);
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics.is_empty());
+        assert!(db_state.users.is_empty());
+    }
+
+    #[test]
+    fn undo_should_refuse_to_overwrite_later_writes() {
+        let mut db = JiraDatabase::with_database(Box::new(MockDB::new()))
+            .with_actor(Some("alice".to_owned()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        db.actor = Some("bob".to_owned());
+        db.update_story(
+            story_id,
+            StoryUpdate {
+                name: Some("renamed".to_owned()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let other_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+
+        db.actor = Some("alice".to_owned());
+        let result = db.undo();
+        assert!(matches!(
+            result.unwrap_err().current_context(),
+            JiraDatabaseError::Conflict
+        ));
+        assert_eq!(db.read_db().unwrap().stories[&story_id].name, "renamed");
+
+        db.actor = Some("bob".to_owned());
+        db.undo().unwrap();
+        db.undo().unwrap();
+        db.actor = Some("alice".to_owned());
+        db.undo().unwrap();
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.stories.is_empty());
+        assert!(!db_state.epics.contains_key(&other_id));
+        assert!(db_state.epics.contains_key(&epic_id));
+    }
+
+    #[test]
+    fn undo_should_cap_journal_size() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let description = "x".repeat(JOURNAL_MAX_BYTES / 4);
+        for _ in 0..6 {
+            db.update_epic(
+                epic_id,
+                EpicUpdate {
+                    description: Some(description.clone()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            db.update_epic(
+                epic_id,
+                EpicUpdate {
+                    description: Some("".to_owned()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        let undo = db.read_db().unwrap().undo;
+        let bytes = undo
+            .iter()
+            .map(|entry| serde_json::to_vec(entry).unwrap().len())
+            .sum::<usize>();
+        assert!(undo.len() < 12);
+        assert!(bytes <= JOURNAL_MAX_BYTES);
+    }
+
+    #[test]
+    fn undo_should_keep_the_latest_journal_entries() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        for points in 0..JOURNAL_LIMIT as u32 + 5 {
+            db.update_epic(
+                epic_id,
+                EpicUpdate {
+                    name: Some(points.to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(db.read_db().unwrap().undo.len(), JOURNAL_LIMIT);
+        for _ in 0..JOURNAL_LIMIT {
+            db.undo().unwrap();
+        }
+        assert!(db.undo().is_err());
+        assert_eq!(db.read_db().unwrap().epics[&epic_id].name, "4");
+    }
+
+    #[test]
+    fn create_user_should_reject_duplicate_username() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let id = db
+            .create_user(User::new("jdoe".to_owned(), "Jane Doe".to_owned()))
+            .unwrap();
+        assert!(db
+            .create_user(User::new("jdoe".to_owned(), "John Doe".to_owned()))
+            .is_err());
+
+        assert_eq!(db.ensure_user("jdoe").unwrap(), id);
+        assert_eq!(db.read_db().unwrap().users.len(), 1);
+    }
+
+    #[test]
+    fn users_comments_and_links_should_not_take_item_ids() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let user_id = db
+            .create_user(User::new("jdoe".to_owned(), "Jane Doe".to_owned()))
+            .unwrap();
+        let comment_id = db
+            .add_comment(epic_id, Some(user_id), "first".to_owned())
+            .unwrap();
+        let first = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let second = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let link_id = db
+            .add_link(Link::new(first, LinkKind::RelatesTo, second))
+            .unwrap();
+
+        assert_eq!((epic_id, first, second), (1, 2, 3));
+        assert_eq!((user_id, comment_id, link_id), (1, 1, 1));
+    }
+
+    #[test]
+    fn assign_story_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let user_id = db.ensure_user("jdoe").unwrap();
+
+        assert!(db.assign_story(story_id, Some(999)).is_err());
+
+        db.assign_story(story_id, Some(user_id)).unwrap();
+        db.assign_epic(epic_id, Some(user_id)).unwrap();
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories[&story_id].assignee, Some(user_id));
+        assert_eq!(db_state.epics[&epic_id].assignee, Some(user_id));
+
+        db.assign_story(story_id, None).unwrap();
+        assert_eq!(db.read_db().unwrap().stories[&story_id].assignee, None);
+    }
+
+    #[test]
+    fn update_epic_status_should_error_if_invalid_epic_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let non_existent_epic_id = 999;
+
+        let result = db.update_epic_status(non_existent_epic_id, Status::Closed);
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn update_epic_status_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+
+        assert_eq!(result.is_ok(), true);
+
+        let epic_id = result.unwrap();
+
+        let result = db.update_epic_status(epic_id, Status::Closed);
+
+        assert_eq!(result.is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+
+        assert_eq!(db_state.epics.get(&epic_id).unwrap().status, Status::Closed);
+    }
+
+    #[test]
+    fn update_status_should_follow_workflows() {
+        let story_workflow = Workflow {
+            states: vec![
+                Status::Open,
+                Status::InProgress,
+                Status::Custom("review".to_owned()),
+                Status::Closed,
+            ],
+            transitions: HashMap::from([
+                (Status::Open, vec![Status::InProgress]),
+                (
+                    Status::InProgress,
+                    vec![Status::Custom("review".to_owned()), Status::Open],
+                ),
+            ]),
+            done: vec![Status::Closed],
+        };
+        let db = JiraDatabase::with_database(Box::new(MockDB::new())).with_workflows(Workflows {
+            epic: Workflow::default(),
+            story: story_workflow,
+        });
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        assert!(db.update_story_status(story_id, Status::Closed).is_err());
+        assert!(db.update_story_status(story_id, Status::Resolved).is_err());
+        db.update_story_status(story_id, Status::Open).unwrap();
+        db.update_story_status(story_id, Status::InProgress)
+            .unwrap();
+        db.update_story_status(story_id, "Review".parse().unwrap())
+            .unwrap();
+        // Review has no transitions listed, so anything in the workflow goes.
+        db.update_story_status(story_id, Status::Closed).unwrap();
+
+        assert!(db
+            .update_epic_status(epic_id, Status::Custom("review".to_owned()))
+            .is_err());
+        db.update_epic_status(epic_id, Status::Resolved).unwrap();
+    }
+
+    #[test]
+    fn done_states_should_come_from_the_workflow() {
+        let shipped = Status::Custom("shipped".to_owned());
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()))
+            .with_workflows(Workflows {
+                epic: Workflow::default(),
+                story: Workflow {
+                    states: vec![Status::Open, Status::Resolved, shipped.clone()],
+                    transitions: HashMap::new(),
+                    done: vec![shipped.clone()],
+                },
+            })
+            .with_invariants(Invariants {
+                close_epic: CloseEpicRule::Block,
+                ..Default::default()
+            });
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(
+                Story {
+                    points: Some(3),
+                    due_date: chrono::NaiveDate::from_ymd_opt(2024, 2, 29),
+                    ..Story::new("".to_owned(), "".to_owned())
+                },
+                epic_id,
+            )
+            .unwrap();
+        let blocked_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        db.add_link(Link::new(story_id, LinkKind::Blocks, blocked_id))
+            .unwrap();
+
+        db.update_story_status(story_id, Status::Resolved).unwrap();
+        assert!(db.update_story_status(blocked_id, shipped.clone()).is_err());
+        assert!(db.update_epic_status(epic_id, Status::Closed).is_err());
+
+        db.update_story_status(story_id, shipped.clone()).unwrap();
+        db.update_story_status(blocked_id, shipped).unwrap();
+        let db_state = db.read_db().unwrap();
+        let epic = &db_state.epics[&epic_id];
+        assert_eq!(db_state.progress(epic, &db.workflows.story).completed, 3);
+        let today = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert!(!db_state.stories[&story_id].is_overdue(&db.workflows.story, today));
+        db.update_epic_status(epic_id, Status::Closed).unwrap();
+    }
+
+    #[test]
+    fn create_should_start_items_in_the_first_workflow_state() {
+        let todo = Status::Custom("todo".to_owned());
+        let workflow = Workflow {
+            states: vec![
+                todo.clone(),
+                Status::Custom("doing".to_owned()),
+                Status::Custom("done".to_owned()),
+            ],
+            transitions: HashMap::from([(todo.clone(), vec![Status::Custom("doing".to_owned())])]),
+            done: vec![Status::Custom("done".to_owned())],
+        };
+        let db = JiraDatabase::with_database(Box::new(MockDB::new())).with_workflows(Workflows {
+            epic: workflow.clone(),
+            story: workflow,
+        });
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&epic_id].status, todo);
+        assert_eq!(db_state.stories[&story_id].status, todo);
+        assert!(db
+            .update_story_status(story_id, Status::Custom("done".to_owned()))
+            .is_err());
+        assert_eq!(
+            db.next_states(story_id).unwrap(),
+            vec![Status::Custom("doing".to_owned())]
+        );
+    }
+
+    #[test]
+    fn update_epic_status_should_follow_close_epic_rule() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new())).with_invariants(Invariants {
+            close_epic: CloseEpicRule::Block,
+            ..Default::default()
+        });
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let open_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let resolved_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        db.update_story_status(resolved_id, Status::Resolved)
+            .unwrap();
+
+        let result = db.update_epic_status(epic_id, Status::Closed);
+        assert!(matches!(
+            result.unwrap_err().current_context(),
+            JiraDatabaseError::InvariantViolation
+        ));
+        db.update_epic_status(epic_id, Status::InProgress).unwrap();
+
+        let db = db.with_invariants(Invariants {
+            close_epic: CloseEpicRule::Cascade,
+            ..Default::default()
+        });
+        db.update_epic_status(epic_id, Status::Closed).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&epic_id].status, Status::Closed);
+        assert_eq!(db_state.stories[&open_id].status, Status::Closed);
+        assert_eq!(db_state.stories[&resolved_id].status, Status::Resolved);
+    }
+
+    #[test]
+    fn close_epic_block_rule_should_keep_stories_of_closed_epics_done() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new())).with_invariants(Invariants {
+            close_epic: CloseEpicRule::Block,
+            ..Default::default()
+        });
+        let closed_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let open_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let done_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), closed_id)
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), open_id)
+            .unwrap();
+        db.update_story_status(done_id, Status::Resolved).unwrap();
+        db.update_epic_status(closed_id, Status::Closed).unwrap();
+
+        fn is_violation<T: std::fmt::Debug>(result: Result<T, JiraDatabaseError>) -> bool {
+            matches!(
+                result.unwrap_err().current_context(),
+                JiraDatabaseError::InvariantViolation
+            )
+        }
+        assert!(is_violation(db.update_story_status(done_id, Status::Open)));
+        assert!(is_violation(
+            db.update_story_status(done_id, Status::InProgress)
+        ));
+        assert!(is_violation(db.create_story(
+            Story::new("".to_owned(), "".to_owned()),
+            closed_id
+        )));
+        assert!(is_violation(db.move_story(story_id, open_id, closed_id)));
+
+        db.update_story_status(done_id, Status::Closed).unwrap();
+        db.update_story_status(story_id, Status::Resolved).unwrap();
+        db.move_story(story_id, open_id, closed_id).unwrap();
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&closed_id].stories, vec![done_id, story_id]);
+        assert_eq!(db_state.stories.len(), 2);
+    }
+
+    #[test]
+    fn update_story_status_should_start_epic() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new())).with_invariants(Invariants {
+            start_epic_with_story: true,
+            ..Default::default()
+        });
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        db.update_story_status(story_id, Status::Resolved).unwrap();
+        assert_eq!(db.read_db().unwrap().epics[&epic_id].status, Status::Open);
+
+        db.update_story_status(story_id, Status::InProgress)
+            .unwrap();
+        assert_eq!(
+            db.read_db().unwrap().epics[&epic_id].status,
+            Status::InProgress
+        );
+    }
+
+    #[test]
+    fn update_story_status_should_start_epic_in_custom_workflows() {
+        let workflow = Workflow {
+            states: vec![
+                Status::Custom("todo".to_owned()),
+                Status::Custom("doing".to_owned()),
+                Status::Custom("done".to_owned()),
+            ],
+            transitions: HashMap::new(),
+            done: vec![Status::Custom("done".to_owned())],
+        };
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()))
+            .with_workflows(Workflows {
+                epic: workflow.clone(),
+                story: workflow,
+            })
+            .with_invariants(Invariants {
+                start_epic_with_story: true,
+                ..Default::default()
+            });
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        db.update_story_status(story_id, Status::Custom("doing".to_owned()))
+            .unwrap();
+        assert_eq!(
+            db.read_db().unwrap().epics[&epic_id].status,
+            Status::Custom("doing".to_owned())
+        );
+    }
+
+    #[test]
+    fn subtasks_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        assert!(db
+            .create_subtask(SubTask::new("".to_owned(), "".to_owned()), 999)
+            .is_err());
+        let first_id = db
+            .create_subtask(SubTask::new("first".to_owned(), "".to_owned()), story_id)
+            .unwrap();
+        let second_id = db
+            .create_subtask(SubTask::new("second".to_owned(), "".to_owned()), story_id)
+            .unwrap();
+        assert_eq!(second_id, 4);
+
+        db.update_subtask_status(first_id, Status::Resolved)
+            .unwrap();
+        assert!(db
+            .update_subtask_status(first_id, Status::Custom("review".to_owned()))
+            .is_err());
+
+        let db_state = db.read_db().unwrap();
+        let story = &db_state.stories[&story_id];
+        let checklist = db_state.checklist(story);
+        assert_eq!(
+            checklist
+                .iter()
+                .map(|(id, subtask)| (*id, Workflow::default().is_done(&subtask.status)))
+                .collect::<Vec<_>>(),
+            vec![(first_id, true), (second_id, false)]
+        );
+        assert_eq!(db_state.history_of(first_id).len(), 2);
+
+        assert!(db.delete_subtask(story_id, 999).is_err());
+        db.delete_subtask(story_id, first_id).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories[&story_id].subtasks, vec![second_id]);
+        assert!(!db_state.subtasks.contains_key(&first_id));
+    }
+
+    #[test]
+    fn delete_story_should_trash_its_subtasks() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let subtask_id = db
+            .create_subtask(SubTask::new("".to_owned(), "".to_owned()), story_id)
+            .unwrap();
+
+        db.delete_story(epic_id, story_id).unwrap();
+        assert!(db.read_db().unwrap().subtasks.is_empty());
+
+        db.restore(story_id).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories[&story_id].subtasks, vec![subtask_id]);
+        assert!(db_state.subtasks.contains_key(&subtask_id));
+        assert_eq!(
+            db_state.history_of(subtask_id).last().unwrap().field,
+            "restored"
+        );
+    }
+
+    #[test]
+    fn add_link_should_reject_cycles() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let ids = (0..3)
+            .map(|_| {
+                db.create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        db.add_link(Link::new(ids[0], LinkKind::Blocks, ids[1]))
+            .unwrap();
+        db.add_link(Link::new(ids[1], LinkKind::Blocks, ids[2]))
+            .unwrap();
+        db.add_link(Link::new(ids[0], LinkKind::RelatesTo, ids[2]))
+            .unwrap();
+
+        let cycle = db
+            .add_link(Link::new(ids[2], LinkKind::Blocks, ids[0]))
+            .unwrap_err();
+        assert!(matches!(
+            cycle.current_context(),
+            JiraDatabaseError::LinkCycle
+        ));
+        assert!(db
+            .add_link(Link::new(ids[2], LinkKind::RelatesTo, ids[0]))
+            .is_err());
+        assert!(db
+            .add_link(Link::new(ids[0], LinkKind::Blocks, ids[0]))
+            .is_err());
+        assert!(db
+            .add_link(Link::new(ids[0], LinkKind::Duplicates, 999))
+            .is_err());
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.links.len(), 3);
+        assert_eq!(db_state.links_of(ids[1]).len(), 2);
+        assert_eq!(
+            db_state.history_of(ids[1]).last().unwrap().new,
+            format!("blocks {}", ids[2])
+        );
+    }
+
+    #[test]
+    fn update_story_status_should_wait_for_blockers() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let blocker_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let link_id = db
+            .add_link(Link::new(blocker_id, LinkKind::Blocks, story_id))
+            .unwrap();
+
+        let blocked = db
+            .update_story_status(story_id, Status::Resolved)
+            .unwrap_err();
+        assert!(matches!(
+            blocked.current_context(),
+            JiraDatabaseError::Blocked
+        ));
+        db.update_story_status(story_id, Status::InProgress)
+            .unwrap();
+
+        // Closing the epic closes the blocker along with the story.
+        db.update_epic_status_cascading(epic_id, Status::Closed)
+            .unwrap();
+        assert_eq!(
+            db.read_db().unwrap().stories[&story_id].status,
+            Status::Closed
+        );
+
+        // Links to purged stories go with them.
+        db.delete_story(epic_id, blocker_id).unwrap();
+        db.purge_trash(Duration::zero()).unwrap();
+        assert!(!db.read_db().unwrap().links.contains_key(&link_id));
+    }
+
+    #[test]
+    fn update_story_status_should_error_if_invalid_story_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let non_existent_story_id = 999;
+
+        let result = db.update_story_status(non_existent_story_id, Status::Closed);
+        assert_eq!(result.is_err(), true);
+        assert!(matches!(
+            result.unwrap_err().current_context(),
+            JiraDatabaseError::NoStoryWithID
+        ));
+    }
+
+    #[test]
+    fn update_story_status_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic = Epic::new("".to_owned(), "".to_owned());
+        let story = Story::new("".to_owned(), "".to_owned());
+
+        let result = db.create_epic(epic);
+
+        let epic_id = result.unwrap();
+
+        let result = db.create_story(story, epic_id);
+
+        let story_id = result.unwrap();
+
+        let result = db.update_story_status(story_id, Status::Closed);
+
+        assert_eq!(result.is_ok(), true);
+
+        let db_state = db.read_db().unwrap();
+
+        assert_eq!(
+            db_state.stories.get(&story_id).unwrap().status,
+            Status::Closed
+        );
+    }
+
+    #[test]
+    fn update_epic_should_error_if_invalid_epic_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let result = db.update_epic(999, EpicUpdate::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn update_epic_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("name".to_owned(), "description".to_owned()))
+            .unwrap();
+
+        let update = EpicUpdate {
+            name: Some("new name".to_owned()),
+            description: None,
+            priority: Some(Priority::Blocker),
+            due_date: None,
+        };
+        assert!(db.update_epic(epic_id, update).is_ok());
+
+        let db_state = db.read_db().unwrap();
+        let epic = db_state.epics.get(&epic_id).unwrap();
+        assert_eq!(epic.name, "new name");
+        assert_eq!(epic.description, "description");
+        assert_eq!(epic.priority, Priority::Blocker);
+    }
+
+    #[test]
+    fn update_story_should_error_if_invalid_story_id() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let result = db.update_story(999, StoryUpdate::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn update_story_should_work() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(
+                Story::new("name".to_owned(), "description".to_owned()),
+                epic_id,
+            )
+            .unwrap();
+
+        let update = StoryUpdate {
+            name: None,
+            description: Some("new description".to_owned()),
+            priority: None,
+            points: Some(Some(3)),
+            due_date: None,
+        };
+        assert!(db.update_story(story_id, update).is_ok());
+
+        let db_state = db.read_db().unwrap();
+        let story = db_state.stories.get(&story_id).unwrap();
+        assert_eq!(story.name, "name");
+        assert_eq!(story.description, "new description");
+        assert_eq!(story.points, Some(3));
+    }
+
+    #[test]
+    fn writes_should_bump_revision() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        db.update_epic_status(epic_id, Status::Closed).unwrap();
+
+        assert_eq!(db.read_db().unwrap().revision, 2);
+    }
+
+    #[test]
+    fn writes_should_error_on_stale_revision() {
+        let db = JiraDatabase::with_database(Box::new(MockDB::new()));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+
+        let mut db_state = db.database.read_db().unwrap();
+        db_state.revision += 1;
+        db.database.write_db(&db_state).unwrap();
+
+        let result = db.update_epic_status(epic_id, Status::Closed);
+        assert!(matches!(
+            result.unwrap_err().current_context(),
+            JiraDatabaseError::Conflict
+        ));
+
+        db.read_db().unwrap();
+        assert!(db.update_epic_status(epic_id, Status::Closed).is_ok());
+    }
+
+    mod database {
+        use std::collections::HashMap;
+        use std::fs::remove_file;
+        use std::io::Write;
+
+        use super::*;
+        use crate::models::Priority;
+
+        #[test]
+        fn read_db_should_fail_with_invalid_path() {
+            let db = JSONFileDatabase::new("INVALID_PATH".to_owned());
+            assert_eq!(db.read_db().is_err(), true);
+        }
+
+        #[test]
+        fn read_db_should_fail_with_invalid_json() {
+            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+            let file_contents = r#"{ "last_item_id": 0 epics: {} stories {} }"#;
+            write!(tmpfile, "{}", file_contents).unwrap();
+
+            let file_path = "./data/read_db_should_fail_with_invalid_json.json".to_owned();
+
+            let path = tmpfile.into_temp_path();
+            path.persist(&file_path).unwrap();
+
+            let db = JSONFileDatabase::new(file_path.clone());
+
+            let result = db.read_db();
+
+            remove_file(file_path).unwrap();
+
+            assert_eq!(result.is_err(), true);
+        }
+
+        #[test]
+        fn read_db_should_parse_json_file() {
+            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+            let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
+            write!(tmpfile, "{}", file_contents).unwrap();
+
+            let file_path = "./data/read_db_should_parse_json_file.json".to_owned();
+
+            let path = tmpfile.into_temp_path();
+            path.persist(&file_path).unwrap();
+
+            let db = JSONFileDatabase::new(file_path.clone());
+
+            let result = db.read_db();
+
+            remove_file(file_path).unwrap();
+
+            assert_eq!(result.is_ok(), true);
+        }
+
+        #[test]
+        fn write_db_should_work() {
+            let mut tmpfile = tempfile::NamedTempFile::new().unwrap();
+
+            let file_contents = r#"{ "last_item_id": 0, "epics": {}, "stories": {} }"#;
+            write!(tmpfile, "{}", file_contents).unwrap();
+
+            let file_path = "./data/write_db_should_work.json".to_owned();
+
+            let path = tmpfile.into_temp_path();
+            path.persist(&file_path).unwrap();
+
+            let db = JSONFileDatabase::new(file_path.clone());
+
+            let story = Story {
+                name: "epic 1".to_owned(),
+                description: "epic 1".to_owned(),
+                status: Status::Open,
+                priority: Priority::Major,
+                points: None,
+                assignee: None,
+                reporter: None,
+                labels: Default::default(),
+                created_at: None,
+                updated_at: None,
+                due_date: None,
+                subtasks: vec![],
+            };
+            let epic = Epic {
+                name: "epic 1".to_owned(),
+                description: "epic 1".to_owned(),
+                status: Status::Open,
+                priority: Priority::Major,
+                stories: vec![2],
+                assignee: None,
+                reporter: None,
+                labels: Default::default(),
+                created_at: None,
+                updated_at: None,
+                due_date: None,
+            };
+
+            let mut stories = HashMap::new();
+            stories.insert(2, story);
+
+            let mut epics = HashMap::new();
+            epics.insert(1, epic);
+
+            let state = DBState {
+                schema_version: CURRENT_SCHEMA_VERSION,
+                last_item_id: 2,
+                last_user_id: 0,
+                last_comment_id: 0,
+                last_link_id: 0,
+                revision: 0,
+                epics,
+                stories,
+                subtasks: HashMap::new(),
+                users: HashMap::new(),
+                comments: HashMap::new(),
+                links: HashMap::new(),
+                history: Vec::new(),
+                undo: Vec::new(),
+                redo: Vec::new(),
+                trash: HashMap::new(),
+            };
+
+            let write_result = db.write_db(&state);
+            let read_result = db.read_db().unwrap();
+
+            remove_file(db.backup_path(1)).unwrap();
+            remove_file(file_path).unwrap();
+
+            assert_eq!(write_result.is_ok(), true);
+            assert_eq!(read_result, state);
+        }
+
+        #[test]
+        fn write_db_should_rotate_backups() {
+            let dir = tempfile::tempdir().unwrap();
+            let file_path = dir.path().join("db.json").to_string_lossy().into_owned();
+
+            let mut db = JSONFileDatabase::new(file_path);
+            db.backup_count = 2;
+
+            let mut state = DBState {
+                schema_version: CURRENT_SCHEMA_VERSION,
+                last_item_id: 0,
+                last_user_id: 0,
+                last_comment_id: 0,
+                last_link_id: 0,
+                revision: 0,
+                epics: HashMap::new(),
+                stories: HashMap::new(),
+                subtasks: HashMap::new(),
+                users: HashMap::new(),
+                comments: HashMap::new(),
+                links: HashMap::new(),
+                history: Vec::new(),
+                undo: Vec::new(),
+                redo: Vec::new(),
+                trash: HashMap::new(),
+            };
+            for last_item_id in 1..=4 {
+                state.last_item_id = last_item_id;
+                db.write_db(&state).unwrap();
+            }
+
+            let tmp_path = format!("{}.tmp", db.file_path);
+
+            assert_eq!(db.read_db().unwrap().last_item_id, 4);
+            assert_eq!(
+                JSONFileDatabase::parse_file(&db.backup_path(1))
+                    .unwrap()
+                    .last_item_id,
+                3
+            );
+            assert_eq!(
+                JSONFileDatabase::parse_file(&db.backup_path(2))
+                    .unwrap()
+                    .last_item_id,
+                2
+            );
+            assert!(!std::path::Path::new(&db.backup_path(3)).exists());
+            assert!(!std::path::Path::new(&tmp_path).exists());
+        }
+
+        #[test]
+        fn recover_should_restore_latest_good_backup() {
+            let dir = tempfile::tempdir().unwrap();
+            let file_path = dir.path().join("db.json").to_string_lossy().into_owned();
+            let db = JSONFileDatabase::new(file_path.clone());
+
+            let state = DBState {
+                schema_version: CURRENT_SCHEMA_VERSION,
+                last_item_id: 7,
+                last_user_id: 0,
+                last_comment_id: 0,
+                last_link_id: 0,
+                revision: 0,
+                epics: HashMap::new(),
+                stories: HashMap::new(),
+                subtasks: HashMap::new(),
+                users: HashMap::new(),
+                comments: HashMap::new(),
+                links: HashMap::new(),
+                history: Vec::new(),
+                undo: Vec::new(),
+                redo: Vec::new(),
+                trash: HashMap::new(),
+            };
+            db.write_db(&state).unwrap();
+            db.write_db(&state).unwrap();
+
+            assert!(!db.recover().unwrap());
+
+            std::fs::write(&file_path, r#"{ "last_item_id": 7, "epi"#).unwrap();
+            assert!(db.read_db().is_err());
+
+            assert!(db.recover().unwrap());
+            assert_eq!(db.read_db().unwrap(), state);
+        }
+
+        #[test]
+        fn recover_should_fail_without_good_backup() {
+            let dir = tempfile::tempdir().unwrap();
+            let file_path = dir.path().join("db.json").to_string_lossy().into_owned();
+            std::fs::write(&file_path, "not json").unwrap();
+
+            let db = JSONFileDatabase::new(file_path);
+            assert!(db.recover().is_err());
+        }
+
+        #[test]
+        fn migrate_should_back_up_and_upgrade_old_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let file_path = dir.path().join("db.json").to_string_lossy().into_owned();
+            let file_contents = r#"{ "last_item_id": 1, "epics": { "1": { "id": 1, "name": "", "description": "", "status": "Open", "stories": [] } }, "stories": {} }"#;
+            std::fs::write(&file_path, file_contents).unwrap();
+
+            let db = JSONFileDatabase::new(file_path.clone());
+
+            let steps = CURRENT_SCHEMA_VERSION as usize;
+            assert_eq!(db.migrate(true).unwrap().len(), steps);
+            assert_eq!(std::fs::read_to_string(&file_path).unwrap(), file_contents);
+
+            assert_eq!(db.migrate(false).unwrap().len(), steps);
+            let backup = std::fs::read_to_string(format!("{}.v0.bak", file_path)).unwrap();
+            assert_eq!(backup, file_contents);
+
+            let raw = JSONFileDatabase::parse_raw(&file_path).unwrap();
+            assert_eq!(migrations::schema_version(&raw), CURRENT_SCHEMA_VERSION);
+            assert!(db.migrate(false).unwrap().is_empty());
+        }
+
+        #[test]
+        fn init_should_create_missing_database() {
+            let dir = tempfile::tempdir().unwrap();
+            let file_path = dir
+                .path()
+                .join("nested/db.json")
+                .to_string_lossy()
+                .into_owned();
+            let db = JiraDatabase::new(file_path);
+
+            assert!(!db.exists());
+            assert!(matches!(
+                db.read_db().unwrap_err().current_context(),
+                JiraDatabaseError::Missing
+            ));
+
+            db.init(false).unwrap();
+
+            assert_eq!(db.read_db().unwrap(), DBState::new());
+            assert!(matches!(
+                db.init(false).unwrap_err().current_context(),
+                JiraDatabaseError::AlreadyExists
+            ));
+        }
+
+        #[test]
+        fn init_should_seed_sample_project() {
+            let dir = tempfile::tempdir().unwrap();
+            let file_path = dir.path().join("db.sqlite").to_string_lossy().into_owned();
+            let db = JiraDatabase::new(file_path);
+
+            db.init(true).unwrap();
+
+            let db_state = db.read_db().unwrap();
+            assert_eq!(db_state.epics.len(), 1);
+            assert_eq!(db_state.stories.len(), 2);
+            assert!(db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .is_ok());
+        }
+
+        #[test]
+        fn backend_should_be_chosen_by_extension() {
+            assert_eq!(
+                DatabaseBackend::from_path("data/db.json"),
+                DatabaseBackend::Json
+            );
+            assert_eq!(DatabaseBackend::from_path("data/db"), DatabaseBackend::Json);
+            assert_eq!(
+                DatabaseBackend::from_path("data/db.db"),
+                DatabaseBackend::SQLite
+            );
+            assert_eq!(
+                DatabaseBackend::from_path("data/db.sqlite3"),
+                DatabaseBackend::SQLite
+            );
+        }
+    }
+}
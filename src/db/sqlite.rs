@@ -0,0 +1,1390 @@
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap};
+
+use error_stack::{IntoReport, Result, ResultExt};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use super::{
+    create_parent_dir, lock::DEFAULT_LOCK_TIMEOUT, Database, DatabaseError, DatabaseLock, Sections,
+    CURRENT_SCHEMA_VERSION,
+};
+use crate::models::{
+    AuditEvent, Comment, DBState, Epic, JournalEntry, Link, LinkKind, Priority, Status, Story,
+    SubTask, TrashEntry, User,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS epics (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS stories (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS epic_stories (
+        epic_id INTEGER NOT NULL REFERENCES epics(id) ON DELETE CASCADE,
+        story_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (epic_id, story_id)
+    );
+    CREATE TABLE IF NOT EXISTS subtasks (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL,
+        status TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS story_subtasks (
+        story_id INTEGER NOT NULL,
+        subtask_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (story_id, subtask_id)
+    );
+    CREATE TABLE IF NOT EXISTS item_labels (
+        item_id INTEGER NOT NULL,
+        label TEXT NOT NULL,
+        PRIMARY KEY (item_id, label)
+    );
+    CREATE TABLE IF NOT EXISTS users (
+        id INTEGER PRIMARY KEY,
+        username TEXT NOT NULL UNIQUE,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS comments (
+        id INTEGER PRIMARY KEY,
+        item_id INTEGER NOT NULL,
+        author INTEGER,
+        created_at TEXT NOT NULL,
+        edited_at TEXT,
+        body TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS links (
+        id INTEGER PRIMARY KEY,
+        from_id INTEGER NOT NULL,
+        to_id INTEGER NOT NULL,
+        kind TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        seq INTEGER PRIMARY KEY,
+        at TEXT NOT NULL,
+        actor TEXT,
+        item_id INTEGER NOT NULL,
+        field TEXT NOT NULL,
+        old_value TEXT NOT NULL,
+        new_value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS journal (
+        stack TEXT NOT NULL,
+        seq INTEGER NOT NULL,
+        entry TEXT NOT NULL,
+        PRIMARY KEY (stack, seq)
+    );
+    CREATE TABLE IF NOT EXISTS trash (
+        id INTEGER PRIMARY KEY,
+        deleted_at TEXT NOT NULL,
+        entry TEXT NOT NULL
+    );
+";
+
+/// Columns added to `SCHEMA` tables after their creation, as `(table, column,
+/// definition)`. Missing ones are added to older files when they are opened.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("epics", "assignee", "INTEGER"),
+    ("epics", "reporter", "INTEGER"),
+    ("stories", "assignee", "INTEGER"),
+    ("stories", "reporter", "INTEGER"),
+    ("epics", "priority", "TEXT NOT NULL DEFAULT 'Major'"),
+    ("stories", "priority", "TEXT NOT NULL DEFAULT 'Major'"),
+    ("stories", "points", "INTEGER"),
+    ("epics", "created_at", "TEXT"),
+    ("epics", "updated_at", "TEXT"),
+    ("epics", "due_date", "TEXT"),
+    ("stories", "created_at", "TEXT"),
+    ("stories", "updated_at", "TEXT"),
+    ("stories", "due_date", "TEXT"),
+];
+
+pub struct SQLiteDatabase {
+    pub file_path: String,
+    pub lock_timeout: std::time::Duration,
+    /// Whether the tables have been created and upgraded by this instance.
+    prepared: Cell<bool>,
+}
+
+impl SQLiteDatabase {
+    pub fn new(file_path: String) -> Self {
+        Self {
+            file_path,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            prepared: Cell::new(false),
+        }
+    }
+
+    /// Opens the file, setting up its tables on the first open only.
+    fn open(&self, flags: OpenFlags) -> Result<Connection, DatabaseError> {
+        let connection = Connection::open_with_flags(&self.file_path, flags)
+            .into_report()
+            .change_context(DatabaseError::ReadError)?;
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .into_report()
+            .change_context(DatabaseError::ReadError)?;
+        if !self.prepared.get() {
+            Self::prepare(&connection)?;
+            self.prepared.set(true);
+        }
+        Ok(connection)
+    }
+
+    /// Creates missing tables and columns and records the schema version,
+    /// refusing files written by a newer build.
+    fn prepare(connection: &Connection) -> Result<(), DatabaseError> {
+        connection
+            .execute_batch(SCHEMA)
+            .into_report()
+            .change_context(DatabaseError::MigrationError)?;
+        let version = Self::find_meta::<u32>(connection, "schema_version")
+            .change_context(DatabaseError::MigrationError)?;
+        if let Some(version) = version.filter(|version| *version > CURRENT_SCHEMA_VERSION) {
+            return Err(DatabaseError::MigrationError)
+                .into_report()
+                .attach_printable(format!(
+                    "Schema version {} is newer than {}",
+                    version, CURRENT_SCHEMA_VERSION
+                ));
+        }
+        Self::add_missing_columns(connection).change_context(DatabaseError::MigrationError)?;
+        if version != Some(CURRENT_SCHEMA_VERSION) {
+            Self::store_meta(connection, "schema_version", CURRENT_SCHEMA_VERSION)
+                .change_context(DatabaseError::MigrationError)?;
+        }
+        Ok(())
+    }
+
+    fn add_missing_columns(connection: &Connection) -> Result<(), rusqlite::Error> {
+        for (table, column, definition) in ADDED_COLUMNS {
+            let exists = connection
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1",
+                        table
+                    ),
+                    params![column],
+                    |row| row.get::<_, u32>(0),
+                )
+                .into_report()?
+                > 0;
+            if !exists {
+                connection
+                    .execute_batch(&format!(
+                        "ALTER TABLE {} ADD COLUMN {} {};",
+                        table, column, definition
+                    ))
+                    .into_report()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_meta<T: rusqlite::types::FromSql + Default>(
+        connection: &Connection,
+        key: &str,
+    ) -> Result<T, rusqlite::Error> {
+        Ok(Self::find_meta(connection, key)?.unwrap_or_default())
+    }
+
+    fn find_meta<T: rusqlite::types::FromSql>(
+        connection: &Connection,
+        key: &str,
+    ) -> Result<Option<T>, rusqlite::Error> {
+        connection
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                params![key],
+                |row| row.get::<_, T>(0),
+            )
+            .optional()
+            .into_report()
+    }
+
+    fn store_meta<T: rusqlite::ToSql>(
+        connection: &Connection,
+        key: &str,
+        value: T,
+    ) -> Result<(), rusqlite::Error> {
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .into_report()?;
+        Ok(())
+    }
+
+    /// Loads the items, and of the history, journal and trash only the
+    /// `sections` asked for; the others are left empty.
+    fn load_state(connection: &Connection, sections: Sections) -> Result<DBState, rusqlite::Error> {
+        let last_item_id = Self::load_meta(connection, "last_item_id")?;
+        // Users, comments and links once shared the item sequence, so files
+        // from before they had their own continue from it.
+        let last_user_id = Self::find_meta(connection, "last_user_id")?.unwrap_or(last_item_id);
+        let last_comment_id =
+            Self::find_meta(connection, "last_comment_id")?.unwrap_or(last_item_id);
+        let last_link_id = Self::find_meta(connection, "last_link_id")?.unwrap_or(last_item_id);
+        let revision = Self::load_meta(connection, "revision")?;
+
+        let mut epic_stories: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut statement = connection
+            .prepare("SELECT epic_id, story_id FROM epic_stories ORDER BY epic_id, position")
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?)))
+            .into_report()?;
+        for row in rows {
+            let (epic_id, story_id) = row.into_report()?;
+            epic_stories.entry(epic_id).or_default().push(story_id);
+        }
+
+        let mut story_subtasks: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut statement = connection
+            .prepare("SELECT story_id, subtask_id FROM story_subtasks ORDER BY story_id, position")
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?)))
+            .into_report()?;
+        for row in rows {
+            let (story_id, subtask_id) = row.into_report()?;
+            story_subtasks.entry(story_id).or_default().push(subtask_id);
+        }
+
+        // Epics and stories share one id sequence, so one table holds both.
+        let mut item_labels: HashMap<u32, BTreeSet<String>> = HashMap::new();
+        let mut statement = connection
+            .prepare("SELECT item_id, label FROM item_labels")
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+            })
+            .into_report()?;
+        for row in rows {
+            let (item_id, label) = row.into_report()?;
+            item_labels.entry(item_id).or_default().insert(label);
+        }
+
+        let mut epics = HashMap::new();
+        let mut statement = connection
+            .prepare(
+                "SELECT id, name, description, status, assignee, reporter, priority, created_at, updated_at, due_date FROM epics",
+            )
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    Epic {
+                        name: row.get(1)?,
+                        description: row.get(2)?,
+                        status: status_from_column(&row.get::<_, String>(3)?)?,
+                        priority: priority_from_column(&row.get::<_, String>(6)?)?,
+                        stories: vec![],
+                        assignee: row.get(4)?,
+                        reporter: row.get(5)?,
+                        labels: BTreeSet::new(),
+                        created_at: row.get(7)?,
+                        updated_at: row.get(8)?,
+                        due_date: row.get(9)?,
+                    },
+                ))
+            })
+            .into_report()?;
+        for row in rows {
+            let (id, mut epic) = row.into_report()?;
+            epic.stories = epic_stories.remove(&id).unwrap_or_default();
+            epic.labels = item_labels.remove(&id).unwrap_or_default();
+            epics.insert(id, epic);
+        }
+
+        let mut stories = HashMap::new();
+        let mut statement = connection
+            .prepare(
+                "SELECT id, name, description, status, assignee, reporter, priority, points, created_at, updated_at, due_date FROM stories",
+            )
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    Story {
+                        name: row.get(1)?,
+                        description: row.get(2)?,
+                        status: status_from_column(&row.get::<_, String>(3)?)?,
+                        priority: priority_from_column(&row.get::<_, String>(6)?)?,
+                        points: row.get(7)?,
+                        assignee: row.get(4)?,
+                        reporter: row.get(5)?,
+                        labels: BTreeSet::new(),
+                        created_at: row.get(8)?,
+                        updated_at: row.get(9)?,
+                        due_date: row.get(10)?,
+                        subtasks: vec![],
+                    },
+                ))
+            })
+            .into_report()?;
+        for row in rows {
+            let (id, mut story) = row.into_report()?;
+            story.labels = item_labels.remove(&id).unwrap_or_default();
+            story.subtasks = story_subtasks.remove(&id).unwrap_or_default();
+            stories.insert(id, story);
+        }
+
+        let mut statement = connection
+            .prepare("SELECT id, name, description, status, created_at, updated_at FROM subtasks")
+            .into_report()?;
+        let subtasks = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    SubTask {
+                        name: row.get(1)?,
+                        description: row.get(2)?,
+                        status: status_from_column(&row.get::<_, String>(3)?)?,
+                        created_at: row.get(4)?,
+                        updated_at: row.get(5)?,
+                    },
+                ))
+            })
+            .into_report()?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()
+            .into_report()?;
+
+        let mut users = HashMap::new();
+        let mut statement = connection
+            .prepare("SELECT id, username, name FROM users")
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, u32>(0)?, User::new(row.get(1)?, row.get(2)?)))
+            })
+            .into_report()?;
+        for row in rows {
+            let (id, user) = row.into_report()?;
+            users.insert(id, user);
+        }
+
+        let mut comments = HashMap::new();
+        let mut statement = connection
+            .prepare("SELECT id, item_id, author, created_at, edited_at, body FROM comments")
+            .into_report()?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    Comment {
+                        item_id: row.get(1)?,
+                        author: row.get(2)?,
+                        created_at: row.get(3)?,
+                        edited_at: row.get(4)?,
+                        body: row.get(5)?,
+                    },
+                ))
+            })
+            .into_report()?;
+        for row in rows {
+            let (id, comment) = row.into_report()?;
+            comments.insert(id, comment);
+        }
+
+        let mut statement = connection
+            .prepare("SELECT id, from_id, to_id, kind FROM links")
+            .into_report()?;
+        let links = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    Link {
+                        from: row.get(1)?,
+                        to: row.get(2)?,
+                        kind: link_kind_from_column(&row.get::<_, String>(3)?)?,
+                    },
+                ))
+            })
+            .into_report()?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()
+            .into_report()?;
+
+        let history = if sections.history {
+            Self::load_history(connection)?
+        } else {
+            vec![]
+        };
+        let (undo, redo) = if sections.journal {
+            (
+                Self::load_journal(connection, "undo")?,
+                Self::load_journal(connection, "redo")?,
+            )
+        } else {
+            (vec![], vec![])
+        };
+        let trash = if sections.trash {
+            Self::load_trash(connection)?
+        } else {
+            HashMap::new()
+        };
+
+        // Tables are always created with the current schema.
+        Ok(DBState {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            last_item_id,
+            last_user_id,
+            last_comment_id,
+            last_link_id,
+            revision,
+            epics,
+            stories,
+            subtasks,
+            users,
+            comments,
+            links,
+            history,
+            undo,
+            redo,
+            trash,
+        })
+    }
+
+    fn load_history(connection: &Connection) -> Result<Vec<AuditEvent>, rusqlite::Error> {
+        let mut statement = connection
+            .prepare(
+                "SELECT at, actor, item_id, field, old_value, new_value FROM history ORDER BY seq",
+            )
+            .into_report()?;
+        let history = statement
+            .query_map([], |row| {
+                Ok(AuditEvent {
+                    at: row.get(0)?,
+                    actor: row.get(1)?,
+                    item_id: row.get(2)?,
+                    field: row.get(3)?,
+                    old: row.get(4)?,
+                    new: row.get(5)?,
+                })
+            })
+            .into_report()?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .into_report()?;
+        Ok(history)
+    }
+
+    fn load_trash(connection: &Connection) -> Result<HashMap<u32, TrashEntry>, rusqlite::Error> {
+        let mut statement = connection
+            .prepare("SELECT id, entry FROM trash")
+            .into_report()?;
+        let trash = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, u32>(0)?, from_json(&row.get::<_, String>(1)?)?))
+            })
+            .into_report()?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()
+            .into_report()?;
+        Ok(trash)
+    }
+
+    /// Journal entries are whole snapshots of the changed items, kept as JSON.
+    fn load_journal(
+        connection: &Connection,
+        stack: &str,
+    ) -> Result<Vec<JournalEntry>, rusqlite::Error> {
+        let mut statement = connection
+            .prepare("SELECT entry FROM journal WHERE stack = ?1 ORDER BY seq")
+            .into_report()?;
+        let entries = statement
+            .query_map(params![stack], |row| from_json(&row.get::<_, String>(0)?))
+            .into_report()?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .into_report()?;
+        Ok(entries)
+    }
+
+    /// Stacks only change at their ends: entries are pushed and popped at
+    /// the top and the oldest dropped at the bottom. So only the rows around
+    /// the longest run of `previous` kept in `entries` are deleted or added;
+    /// the rows of a stack keep consecutive `seq`s.
+    fn store_journal(
+        connection: &Connection,
+        stack: &str,
+        previous: &[JournalEntry],
+        entries: &[JournalEntry],
+    ) -> Result<(), rusqlite::Error> {
+        let (dropped, kept) = (0..=previous.len())
+            .map(|dropped| {
+                let kept = previous[dropped..]
+                    .iter()
+                    .zip(entries)
+                    .take_while(|(before, after)| before == after)
+                    .count();
+                (dropped, kept)
+            })
+            .max_by_key(|(dropped, kept)| (*kept, std::cmp::Reverse(*dropped)))
+            .unwrap_or_default();
+        let first = connection
+            .query_row(
+                "SELECT COALESCE(MIN(seq), 0) FROM journal WHERE stack = ?1",
+                params![stack],
+                |row| row.get::<_, usize>(0),
+            )
+            .into_report()?;
+        let kept_from = first + dropped;
+        let kept_to = kept_from + kept;
+        connection
+            .execute(
+                "DELETE FROM journal WHERE stack = ?1 AND (seq < ?2 OR seq >= ?3)",
+                params![stack, kept_from, kept_to],
+            )
+            .into_report()?;
+        for (seq, entry) in (kept_to..).zip(&entries[kept..]) {
+            let entry = to_json(entry).into_report()?;
+            connection
+                .execute(
+                    "INSERT INTO journal (stack, seq, entry) VALUES (?1, ?2, ?3)",
+                    params![stack, seq, entry],
+                )
+                .into_report()?;
+        }
+        Ok(())
+    }
+
+    fn store_labels(
+        connection: &Connection,
+        item_id: u32,
+        labels: &BTreeSet<String>,
+    ) -> Result<(), rusqlite::Error> {
+        connection
+            .execute(
+                "DELETE FROM item_labels WHERE item_id = ?1",
+                params![item_id],
+            )
+            .into_report()?;
+        for label in labels {
+            connection
+                .execute(
+                    "INSERT INTO item_labels (item_id, label) VALUES (?1, ?2)",
+                    params![item_id, label],
+                )
+                .into_report()?;
+        }
+        Ok(())
+    }
+
+    /// Writes only the rows that differ between `previous` and `db_state`,
+    /// so a status change touches a single row instead of the whole board.
+    /// Both hold the same `sections`; without the history, the events in
+    /// `db_state` are new ones to append.
+    fn store_changes(
+        connection: &Connection,
+        previous: &DBState,
+        db_state: &DBState,
+        sections: Sections,
+    ) -> Result<(), rusqlite::Error> {
+        for (key, previous_id, id) in [
+            ("last_item_id", previous.last_item_id, db_state.last_item_id),
+            ("last_user_id", previous.last_user_id, db_state.last_user_id),
+            (
+                "last_comment_id",
+                previous.last_comment_id,
+                db_state.last_comment_id,
+            ),
+            ("last_link_id", previous.last_link_id, db_state.last_link_id),
+        ] {
+            if previous_id != id {
+                Self::store_meta(connection, key, id)?;
+            }
+        }
+        if previous.revision != db_state.revision {
+            Self::store_meta(connection, "revision", db_state.revision)?;
+        }
+
+        for id in previous.epics.keys() {
+            if !db_state.epics.contains_key(id) {
+                connection
+                    .execute("DELETE FROM epics WHERE id = ?1", params![id])
+                    .into_report()?;
+                Self::store_labels(connection, *id, &BTreeSet::new())?;
+            }
+        }
+        for (id, epic) in &db_state.epics {
+            let stored = previous.epics.get(id);
+            if stored == Some(epic) {
+                continue;
+            }
+            connection
+                .execute(
+                    // An upsert rather than REPLACE, which would delete the
+                    // row and cascade to its `epic_stories`.
+                    "INSERT INTO epics (id, name, description, status, assignee, reporter, priority, created_at, updated_at, due_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT (id) DO UPDATE SET name = excluded.name, description = excluded.description, status = excluded.status, assignee = excluded.assignee, reporter = excluded.reporter, priority = excluded.priority, created_at = excluded.created_at, updated_at = excluded.updated_at, due_date = excluded.due_date",
+                    params![
+                        id,
+                        epic.name,
+                        epic.description,
+                        status_to_column(&epic.status),
+                        epic.assignee,
+                        epic.reporter,
+                        priority_to_column(&epic.priority),
+                        epic.created_at,
+                        epic.updated_at,
+                        epic.due_date
+                    ],
+                )
+                .into_report()?;
+            if stored.map(|stored| &stored.labels) != Some(&epic.labels) {
+                Self::store_labels(connection, *id, &epic.labels)?;
+            }
+            if stored.map(|stored| &stored.stories) != Some(&epic.stories) {
+                connection
+                    .execute("DELETE FROM epic_stories WHERE epic_id = ?1", params![id])
+                    .into_report()?;
+                for (position, story_id) in epic.stories.iter().enumerate() {
+                    connection
+                        .execute(
+                            "INSERT INTO epic_stories (epic_id, story_id, position) VALUES (?1, ?2, ?3)",
+                            params![id, story_id, position],
+                        )
+                        .into_report()?;
+                }
+            }
+        }
+
+        for id in previous.stories.keys() {
+            if !db_state.stories.contains_key(id) {
+                connection
+                    .execute("DELETE FROM stories WHERE id = ?1", params![id])
+                    .into_report()?;
+                connection
+                    .execute(
+                        "DELETE FROM story_subtasks WHERE story_id = ?1",
+                        params![id],
+                    )
+                    .into_report()?;
+                Self::store_labels(connection, *id, &BTreeSet::new())?;
+            }
+        }
+        for (id, story) in &db_state.stories {
+            let stored = previous.stories.get(id);
+            if stored == Some(story) {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO stories (id, name, description, status, assignee, reporter, priority, points, created_at, updated_at, due_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        id,
+                        story.name,
+                        story.description,
+                        status_to_column(&story.status),
+                        story.assignee,
+                        story.reporter,
+                        priority_to_column(&story.priority),
+                        story.points,
+                        story.created_at,
+                        story.updated_at,
+                        story.due_date
+                    ],
+                )
+                .into_report()?;
+            if stored.map(|stored| &stored.labels) != Some(&story.labels) {
+                Self::store_labels(connection, *id, &story.labels)?;
+            }
+            if stored.map(|stored| &stored.subtasks) != Some(&story.subtasks) {
+                connection
+                    .execute(
+                        "DELETE FROM story_subtasks WHERE story_id = ?1",
+                        params![id],
+                    )
+                    .into_report()?;
+                for (position, subtask_id) in story.subtasks.iter().enumerate() {
+                    connection
+                        .execute(
+                            "INSERT INTO story_subtasks (story_id, subtask_id, position) VALUES (?1, ?2, ?3)",
+                            params![id, subtask_id, position],
+                        )
+                        .into_report()?;
+                }
+            }
+        }
+
+        for id in previous.subtasks.keys() {
+            if !db_state.subtasks.contains_key(id) {
+                connection
+                    .execute("DELETE FROM subtasks WHERE id = ?1", params![id])
+                    .into_report()?;
+            }
+        }
+        for (id, subtask) in &db_state.subtasks {
+            if previous.subtasks.get(id) == Some(subtask) {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO subtasks (id, name, description, status, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        id,
+                        subtask.name,
+                        subtask.description,
+                        status_to_column(&subtask.status),
+                        subtask.created_at,
+                        subtask.updated_at
+                    ],
+                )
+                .into_report()?;
+        }
+
+        for id in previous.users.keys() {
+            if !db_state.users.contains_key(id) {
+                connection
+                    .execute("DELETE FROM users WHERE id = ?1", params![id])
+                    .into_report()?;
+            }
+        }
+        for (id, user) in &db_state.users {
+            if previous.users.get(id) == Some(user) {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO users (id, username, name) VALUES (?1, ?2, ?3)",
+                    params![id, user.username, user.name],
+                )
+                .into_report()?;
+        }
+
+        for id in previous.comments.keys() {
+            if !db_state.comments.contains_key(id) {
+                connection
+                    .execute("DELETE FROM comments WHERE id = ?1", params![id])
+                    .into_report()?;
+            }
+        }
+        for (id, comment) in &db_state.comments {
+            if previous.comments.get(id) == Some(comment) {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO comments (id, item_id, author, created_at, edited_at, body) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        id,
+                        comment.item_id,
+                        comment.author,
+                        comment.created_at,
+                        comment.edited_at,
+                        comment.body
+                    ],
+                )
+                .into_report()?;
+        }
+
+        for id in previous.links.keys() {
+            if !db_state.links.contains_key(id) {
+                connection
+                    .execute("DELETE FROM links WHERE id = ?1", params![id])
+                    .into_report()?;
+            }
+        }
+        for (id, link) in &db_state.links {
+            if previous.links.get(id) == Some(link) {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO links (id, from_id, to_id, kind) VALUES (?1, ?2, ?3, ?4)",
+                    params![id, link.from, link.to, link_kind_to_column(link.kind)],
+                )
+                .into_report()?;
+        }
+
+        // History is append-only, rows are keyed by their position.
+        let (first_seq, unchanged) = if sections.history {
+            let unchanged = db_state
+                .history
+                .iter()
+                .zip(&previous.history)
+                .take_while(|(event, stored)| event == stored)
+                .count();
+            if unchanged < previous.history.len() {
+                connection
+                    .execute("DELETE FROM history WHERE seq >= ?1", params![unchanged])
+                    .into_report()?;
+            }
+            (0, unchanged)
+        } else {
+            let next_seq = connection
+                .query_row("SELECT COALESCE(MAX(seq) + 1, 0) FROM history", [], |row| {
+                    row.get::<_, usize>(0)
+                })
+                .into_report()?;
+            (next_seq, 0)
+        };
+        for (seq, event) in db_state.history.iter().enumerate().skip(unchanged) {
+            let seq = first_seq + seq;
+            connection
+                .execute(
+                    "INSERT INTO history (seq, at, actor, item_id, field, old_value, new_value) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        seq,
+                        event.at,
+                        event.actor,
+                        event.item_id,
+                        event.field,
+                        event.old,
+                        event.new
+                    ],
+                )
+                .into_report()?;
+        }
+
+        if !sections.trash {
+            return Self::store_journals(connection, previous, db_state, sections);
+        }
+        for id in previous.trash.keys() {
+            if !db_state.trash.contains_key(id) {
+                connection
+                    .execute("DELETE FROM trash WHERE id = ?1", params![id])
+                    .into_report()?;
+            }
+        }
+        for (id, entry) in &db_state.trash {
+            if previous.trash.get(id) == Some(entry) {
+                continue;
+            }
+            connection
+                .execute(
+                    "INSERT OR REPLACE INTO trash (id, deleted_at, entry) VALUES (?1, ?2, ?3)",
+                    params![id, entry.deleted_at, to_json(entry).into_report()?],
+                )
+                .into_report()?;
+        }
+
+        Self::store_journals(connection, previous, db_state, sections)
+    }
+
+    fn store_journals(
+        connection: &Connection,
+        previous: &DBState,
+        db_state: &DBState,
+        sections: Sections,
+    ) -> Result<(), rusqlite::Error> {
+        if !sections.journal {
+            return Ok(());
+        }
+        if previous.undo != db_state.undo {
+            Self::store_journal(connection, "undo", &previous.undo, &db_state.undo)?;
+        }
+        if previous.redo != db_state.redo {
+            Self::store_journal(connection, "redo", &previous.redo, &db_state.redo)?;
+        }
+        Ok(())
+    }
+}
+
+impl Database for SQLiteDatabase {
+    fn read_db(&self) -> Result<DBState, DatabaseError> {
+        self.read_sections(Sections::ALL)
+    }
+
+    fn write_db(&self, db_state: &DBState) -> Result<(), DatabaseError> {
+        self.write_sections(db_state, None, Sections::ALL)
+    }
+
+    fn read_sections(&self, sections: Sections) -> Result<DBState, DatabaseError> {
+        // Setting up the tables takes write access.
+        let flags = if self.prepared.get() {
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+        };
+        let connection = self.open(flags).change_context(DatabaseError::ReadError)?;
+
+        Self::load_state(&connection, sections).change_context(DatabaseError::ReadError)
+    }
+
+    fn write_sections(
+        &self,
+        db_state: &DBState,
+        previous: Option<&DBState>,
+        sections: Sections,
+    ) -> Result<(), DatabaseError> {
+        let mut connection = self
+            .open(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)
+            .change_context(DatabaseError::WriteError)?;
+
+        let transaction = connection
+            .transaction()
+            .into_report()
+            .change_context(DatabaseError::WriteError)?;
+
+        let loaded;
+        let previous = match previous {
+            Some(previous) => previous,
+            None => {
+                loaded = Self::load_state(&transaction, sections)
+                    .change_context(DatabaseError::WriteError)?;
+                &loaded
+            }
+        };
+        Self::store_changes(&transaction, previous, db_state, sections)
+            .change_context(DatabaseError::WriteError)?;
+
+        transaction
+            .commit()
+            .into_report()
+            .change_context(DatabaseError::WriteError)
+    }
+
+    fn lock(&self) -> Result<DatabaseLock, DatabaseError> {
+        DatabaseLock::acquire(&self.file_path, self.lock_timeout)
+    }
+
+    fn exists(&self) -> bool {
+        std::path::Path::new(&self.file_path).exists()
+    }
+
+    fn create(&self, db_state: &DBState) -> Result<(), DatabaseError> {
+        create_parent_dir(&self.file_path)?;
+        self.write_db(db_state)
+    }
+}
+
+fn status_to_column(status: &Status) -> &str {
+    status.key()
+}
+
+fn status_from_column(column: &str) -> rusqlite::Result<Status> {
+    match column {
+        "" => Err(rusqlite::Error::InvalidColumnType(
+            3,
+            "status".to_owned(),
+            rusqlite::types::Type::Text,
+        )),
+        _ => Ok(Status::from_key(column)),
+    }
+}
+
+fn priority_to_column(priority: &Priority) -> &'static str {
+    match priority {
+        Priority::Blocker => "Blocker",
+        Priority::Critical => "Critical",
+        Priority::Major => "Major",
+        Priority::Minor => "Minor",
+        Priority::Trivial => "Trivial",
+    }
+}
+
+fn link_kind_to_column(kind: LinkKind) -> &'static str {
+    match kind {
+        LinkKind::Blocks => "Blocks",
+        LinkKind::RelatesTo => "RelatesTo",
+        LinkKind::Duplicates => "Duplicates",
+    }
+}
+
+fn link_kind_from_column(column: &str) -> rusqlite::Result<LinkKind> {
+    match column {
+        "Blocks" => Ok(LinkKind::Blocks),
+        "RelatesTo" => Ok(LinkKind::RelatesTo),
+        "Duplicates" => Ok(LinkKind::Duplicates),
+        _ => Err(rusqlite::Error::InvalidColumnType(
+            3,
+            "kind".to_owned(),
+            rusqlite::types::Type::Text,
+        )),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(column: &str) -> rusqlite::Result<T> {
+    serde_json::from_str(column).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(err))
+    })
+}
+
+fn priority_from_column(column: &str) -> rusqlite::Result<Priority> {
+    match column {
+        "Blocker" => Ok(Priority::Blocker),
+        "Critical" => Ok(Priority::Critical),
+        "Major" => Ok(Priority::Major),
+        "Minor" => Ok(Priority::Minor),
+        "Trivial" => Ok(Priority::Trivial),
+        _ => Err(rusqlite::Error::InvalidColumnType(
+            6,
+            "priority".to_owned(),
+            rusqlite::types::Type::Text,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, Utc};
+
+    use super::*;
+    use crate::models::{Change, TrashEntry, TrashedItem};
+
+    fn temp_database() -> (tempfile::TempDir, SQLiteDatabase) {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("db.sqlite").to_string_lossy().into_owned();
+        (dir, SQLiteDatabase::new(file_path))
+    }
+
+    fn sample_state() -> DBState {
+        let mut epics = HashMap::new();
+        epics.insert(
+            1,
+            Epic {
+                name: "epic 1".to_owned(),
+                description: "epic 1".to_owned(),
+                status: Status::InProgress,
+                priority: Priority::Critical,
+                stories: vec![3, 2],
+                assignee: Some(4),
+                reporter: None,
+                labels: BTreeSet::from(["backend".to_owned()]),
+                created_at: Some(Utc::now()),
+                updated_at: Some(Utc::now()),
+                due_date: NaiveDate::from_ymd_opt(2024, 2, 29),
+            },
+        );
+
+        let mut stories = HashMap::new();
+        stories.insert(2, Story::new("story 2".to_owned(), "story 2".to_owned()));
+        let mut story = Story::new("story 3".to_owned(), "story 3".to_owned());
+        story.status = Status::Custom("review".to_owned());
+        story.reporter = Some(4);
+        story.priority = Priority::Trivial;
+        story.points = Some(5);
+        story.due_date = NaiveDate::from_ymd_opt(2024, 3, 1);
+        story.updated_at = Some(Utc::now());
+        story.labels = BTreeSet::from(["backend".to_owned(), "tech-debt".to_owned()]);
+        story.subtasks = vec![9, 8];
+        stories.insert(3, story);
+
+        let mut subtasks = HashMap::new();
+        let mut subtask = SubTask::new("write docs".to_owned(), "".to_owned());
+        subtask.status = Status::Resolved;
+        subtasks.insert(8, subtask);
+        subtasks.insert(9, SubTask::new("add tests".to_owned(), "".to_owned()));
+
+        let mut users = HashMap::new();
+        users.insert(4, User::new("jdoe".to_owned(), "Jane Doe".to_owned()));
+
+        let mut comments = HashMap::new();
+        comments.insert(5, Comment::new(3, Some(4), "Looks good".to_owned()));
+
+        let links = HashMap::from([(10, Link::new(2, LinkKind::Blocks, 3))]);
+
+        let history = vec![AuditEvent {
+            at: Utc::now(),
+            actor: Some("jdoe".to_owned()),
+            item_id: 3,
+            field: "status".to_owned(),
+            old: "OPEN".to_owned(),
+            new: "CLOSED".to_owned(),
+        }];
+
+        let mut closed = stories[&3].clone();
+        closed.status = Status::Closed;
+        let undo = vec![JournalEntry {
+            at: Utc::now(),
+            actor: Some("jdoe".to_owned()),
+            epics: vec![],
+            stories: vec![Change {
+                id: 3,
+                before: Some(stories[&3].clone()),
+                after: Some(closed),
+            }],
+            comments: vec![],
+            users: vec![],
+            trash: vec![],
+            subtasks: vec![],
+            links: vec![],
+        }];
+
+        let trash = HashMap::from([(
+            6,
+            TrashEntry {
+                deleted_at: Utc::now(),
+                item: TrashedItem::Story {
+                    epic_id: 1,
+                    story: Story::new("story 6".to_owned(), "".to_owned()),
+                },
+                comments: HashMap::from([(7, Comment::new(6, None, "Duplicate".to_owned()))]),
+                subtasks: HashMap::new(),
+            },
+        )]);
+
+        DBState {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            last_item_id: 10,
+            last_user_id: 4,
+            last_comment_id: 7,
+            last_link_id: 10,
+            revision: 1,
+            epics,
+            stories,
+            subtasks,
+            users,
+            comments,
+            links,
+            history,
+            undo,
+            redo: vec![],
+            trash,
+        }
+    }
+
+    #[test]
+    fn read_db_should_fail_with_missing_file() {
+        let (_dir, db) = temp_database();
+        assert!(db.read_db().is_err());
+    }
+
+    #[test]
+    fn write_db_should_work() {
+        let (_dir, db) = temp_database();
+        let state = sample_state();
+
+        assert!(db.write_db(&state).is_ok());
+        assert_eq!(db.read_db().unwrap(), state);
+    }
+
+    #[test]
+    fn write_db_should_apply_updates_and_deletes() {
+        let (_dir, db) = temp_database();
+        let mut state = sample_state();
+        db.write_db(&state).unwrap();
+
+        state.stories.get_mut(&2).unwrap().status = Status::Closed;
+        state.epics.get_mut(&1).unwrap().labels.clear();
+        state.comments.get_mut(&5).unwrap().edited_at = Some(Utc::now());
+        state.subtasks.get_mut(&9).unwrap().status = Status::InProgress;
+        state.links.get_mut(&10).unwrap().kind = LinkKind::RelatesTo;
+        state
+            .links
+            .insert(11, Link::new(3, LinkKind::Duplicates, 2));
+        state.last_item_id = 11;
+        state.stories.get_mut(&2).unwrap().subtasks = vec![8];
+        let mut event = state.history[0].clone();
+        event.item_id = 2;
+        state.history.push(event);
+        let entry = state.undo.pop().unwrap();
+        state.redo.push(entry);
+        state.stories.remove(&3);
+        state.epics.get_mut(&1).unwrap().stories = vec![2];
+        db.write_db(&state).unwrap();
+
+        assert_eq!(db.read_db().unwrap(), state);
+
+        state.epics.remove(&1);
+        state.stories.clear();
+        state.users.clear();
+        state.comments.clear();
+        state.subtasks.clear();
+        state.links.clear();
+        state.history.clear();
+        state.redo.clear();
+        state.trash.clear();
+        db.write_db(&state).unwrap();
+
+        assert_eq!(db.read_db().unwrap(), state);
+    }
+
+    #[test]
+    fn write_db_should_only_touch_changed_journal_rows() {
+        let (_dir, db) = temp_database();
+        let mut state = sample_state();
+        let template = state.undo[0].clone();
+        let entry = |actor: &str| JournalEntry {
+            actor: Some(actor.to_owned()),
+            ..template.clone()
+        };
+        state.undo = vec![entry("a"), entry("b"), entry("c")];
+        db.write_db(&state).unwrap();
+        let seqs = || {
+            let connection = Connection::open(&db.file_path).unwrap();
+            let mut statement = connection
+                .prepare("SELECT seq, entry FROM journal WHERE stack = 'undo' ORDER BY seq")
+                .unwrap();
+            let rows = statement
+                .query_map([], |row| {
+                    let entry = from_json::<JournalEntry>(&row.get::<_, String>(1)?)?;
+                    Ok((row.get::<_, usize>(0)?, entry.actor.unwrap_or_default()))
+                })
+                .unwrap()
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .unwrap();
+            rows
+        };
+
+        // The oldest entry dropped for a new one.
+        state.undo.remove(0);
+        state.undo.push(entry("d"));
+        db.write_db(&state).unwrap();
+        assert_eq!(
+            seqs(),
+            vec![
+                (1, "b".to_owned()),
+                (2, "c".to_owned()),
+                (3, "d".to_owned())
+            ]
+        );
+
+        // The latest entry replaced, as when merging writes of one action.
+        state.undo.pop();
+        state.undo.push(entry("e"));
+        db.write_db(&state).unwrap();
+        assert_eq!(
+            seqs(),
+            vec![
+                (1, "b".to_owned()),
+                (2, "c".to_owned()),
+                (3, "e".to_owned())
+            ]
+        );
+        assert_eq!(db.read_db().unwrap().undo, state.undo);
+    }
+
+    #[test]
+    fn write_db_should_keep_stories_of_updated_epic() {
+        let (_dir, db) = temp_database();
+        let mut state = sample_state();
+        db.write_db(&state).unwrap();
+
+        state.epics.get_mut(&1).unwrap().name = "renamed".to_owned();
+        db.write_db(&state).unwrap();
+
+        let stored = db.read_db().unwrap();
+        assert!(!stored.epics[&1].stories.is_empty());
+        assert_eq!(stored.epics[&1].stories, state.epics[&1].stories);
+        assert_eq!(stored, state);
+    }
+
+    #[test]
+    fn write_sections_should_append_history() {
+        let (_dir, db) = temp_database();
+        let state = sample_state();
+        db.write_db(&state).unwrap();
+
+        let mut partial = db.read_sections(Sections::ITEMS).unwrap();
+        assert!(partial.history.is_empty());
+        assert!(partial.undo.is_empty());
+        assert!(partial.trash.is_empty());
+
+        let mut event = state.history[0].clone();
+        event.item_id = 2;
+        partial.history.push(event.clone());
+        partial.stories.get_mut(&2).unwrap().status = Status::Closed;
+        db.write_sections(&partial, None, Sections::ITEMS).unwrap();
+
+        let stored = db.read_db().unwrap();
+        assert_eq!(stored.history, vec![state.history[0].clone(), event]);
+        assert_eq!(stored.undo, state.undo);
+        assert_eq!(stored.trash, state.trash);
+        assert_eq!(stored.stories[&2].status, Status::Closed);
+    }
+
+    #[test]
+    fn write_sections_should_only_write_changes_from_previous() {
+        let (_dir, db) = temp_database();
+        db.write_db(&sample_state()).unwrap();
+        let previous = db.read_sections(Sections::WRITE).unwrap();
+        let connection = Connection::open(&db.file_path).unwrap();
+        connection
+            .execute_batch("UPDATE stories SET name = 'elsewhere' WHERE id = 3;")
+            .unwrap();
+        drop(connection);
+
+        let mut db_state = previous.clone();
+        db_state.stories.get_mut(&2).unwrap().status = Status::Closed;
+        db.write_sections(&db_state, Some(&previous), Sections::WRITE)
+            .unwrap();
+
+        let stored = db.read_db().unwrap();
+        assert_eq!(stored.stories[&2].status, Status::Closed);
+        assert_eq!(stored.stories[&3].name, "elsewhere");
+    }
+
+    #[test]
+    fn read_db_should_add_missing_columns() {
+        let (_dir, db) = temp_database();
+        let connection = Connection::open(&db.file_path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE stories (id INTEGER PRIMARY KEY, name TEXT NOT NULL, description TEXT NOT NULL, status TEXT NOT NULL);
+                 INSERT INTO stories (id, name, description, status) VALUES (1, 'old', '', 'Open');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories[&1].name, "old");
+        assert_eq!(db_state.stories[&1].assignee, None);
+        assert_eq!(db_state.stories[&1].priority, Priority::Major);
+    }
+
+    #[test]
+    fn read_db_should_reject_newer_schema() {
+        let (_dir, db) = temp_database();
+        db.write_db(&sample_state()).unwrap();
+        let connection = Connection::open(&db.file_path).unwrap();
+        let version: u32 = connection
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'schema_version'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(version, CURRENT_SCHEMA_VERSION);
+        connection
+            .execute(
+                "UPDATE meta SET value = ?1 WHERE key = 'schema_version'",
+                params![CURRENT_SCHEMA_VERSION + 1],
+            )
+            .unwrap();
+        drop(connection);
+
+        let db = SQLiteDatabase::new(db.file_path.clone());
+        assert!(db.read_db().is_err());
+        assert!(db.write_db(&sample_state()).is_err());
+    }
+
+    #[test]
+    fn read_db_should_continue_id_sequences_from_item_ids() {
+        let (_dir, db) = temp_database();
+        db.write_db(&sample_state()).unwrap();
+        let connection = Connection::open(&db.file_path).unwrap();
+        connection
+            .execute_batch("DELETE FROM meta WHERE key <> 'last_item_id';")
+            .unwrap();
+        drop(connection);
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.last_user_id, 10);
+        assert_eq!(db_state.last_comment_id, 10);
+        assert_eq!(db_state.last_link_id, 10);
+    }
+}
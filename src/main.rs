@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use clap::Parser;
+
 mod models;
 
 mod db;
@@ -13,12 +15,96 @@ use io_utils::*;
 mod navigator;
 use navigator::*;
 
+mod cli;
+use cli::Cli;
+
+mod config;
+use config::Config;
+
 fn main() {
-    let db = Rc::new(JiraDatabase::new("data/db.json".to_owned()));
-    let mut navigator = Navigator::new(db);
+    let cli = Cli::parse();
+    let interactive = cli.command.is_none();
+
+    let config = match Config::load(cli.config_overrides()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let db = Rc::new(
+        match config.backend {
+            Some(backend) => JiraDatabase::with_backend(config.database_path.clone(), backend),
+            None => JiraDatabase::new(config.database_path.clone()),
+        }
+        .with_actor(config.current_user.clone())
+        .with_workflows(config.workflows.clone())
+        .with_invariants(config.invariants.clone()),
+    );
+
+    match db.recover() {
+        Ok(true) => {
+            eprintln!("Database was unreadable and has been restored from the latest backup.");
+            if interactive {
+                wait_for_key_press();
+            }
+        }
+        Ok(false) => (),
+        Err(e) => {
+            eprintln!("{:?}", e);
+            if interactive {
+                wait_for_key_press();
+            }
+        }
+    }
+
+    if let Some(command) = cli.command {
+        if let Err(e) = cli::run(command, cli.format, &db, config.current_user.as_deref()) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if !db.exists() {
+        match ui::init_database_prompt(&config.database_path) {
+            Some(sample) => {
+                if let Err(e) = db.init(sample) {
+                    eprintln!("{:#}", e);
+                    std::process::exit(1);
+                }
+            }
+            None => return,
+        }
+    }
+
+    match db.migrate(false) {
+        Ok(steps) => {
+            for step in &steps {
+                println!("Applied {}", step);
+            }
+            if !steps.is_empty() {
+                wait_for_key_press();
+            }
+        }
+        Err(e) => {
+            println!("{:?}", e);
+            wait_for_key_press();
+        }
+    }
+
+    let navigator = if config.use_editor {
+        Navigator::with_prompts(db, ui::Prompts::with_editor())
+    } else {
+        Navigator::new(db)
+    };
+    let mut navigator = navigator.with_current_user(config.current_user.clone());
 
     loop {
-        clearscreen::clear().unwrap();
+        if config.clear_screen {
+            clearscreen::clear().unwrap();
+        }
         let cur_page = match navigator.get_current_page() {
             Some(page) => page,
             None => break,
@@ -1,25 +1,50 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Action {
     CreateEpic,
     NavigateToEpicDetail { epic_id: u32 },
     UpdateEpicStatus { epic_id: u32 },
+    EditEpic { epic_id: u32 },
+    EditEpicLabels { epic_id: u32 },
     DeleteEpic { epic_id: u32 },
     CreateStory { epic_id: u32 },
     NavigateToStoryDetail { epic_id: u32, story_id: u32 },
     UpdateStoryStatus { story_id: u32 },
+    EditStory { story_id: u32 },
+    EditStoryLabels { story_id: u32 },
     DeleteStory { epic_id: u32, story_id: u32 },
+    MoveStory { epic_id: u32, story_id: u32 },
+    AssignEpic { epic_id: u32 },
+    AssignStory { story_id: u32 },
+    AddComment { item_id: u32 },
+    NavigateToHistory { item_id: u32 },
+    CreateSubTask { story_id: u32 },
+    NavigateToSubTaskDetail { story_id: u32, subtask_id: u32 },
+    UpdateSubTaskStatus { subtask_id: u32 },
+    DeleteSubTask { story_id: u32, subtask_id: u32 },
+    AddStoryLink { story_id: u32 },
+    RemoveStoryLink { story_id: u32 },
+    Undo,
+    Redo,
+    NavigateToTrash,
+    RestoreFromTrash,
+    NavigateToAssignedToMe,
     NavigateToPreviousPage,
     Exit,
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+/// Serialized as its `key`, so custom states are plain strings too.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Status {
     Open,
     InProgress,
     Resolved,
     Closed,
+    /// A state defined by a configured workflow, by its lowercase name.
+    Custom(String),
 }
 
 impl std::fmt::Display for Status {
@@ -29,6 +54,260 @@ impl std::fmt::Display for Status {
             Self::InProgress => write!(f, "IN PROGRESS"),
             Self::Resolved => write!(f, "RESOLVED"),
             Self::Closed => write!(f, "CLOSED"),
+            Self::Custom(name) => write!(f, "{}", name.to_uppercase()),
+        }
+    }
+}
+
+impl Status {
+    /// Name in the database; custom names are lowercase, so they can't clash
+    /// with the capitalized built-in ones.
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Open => "Open",
+            Self::InProgress => "InProgress",
+            Self::Resolved => "Resolved",
+            Self::Closed => "Closed",
+            Self::Custom(name) => name,
+        }
+    }
+
+    pub fn from_key(key: &str) -> Self {
+        match key {
+            "Open" => Self::Open,
+            "InProgress" => Self::InProgress,
+            "Resolved" => Self::Resolved,
+            "Closed" => Self::Closed,
+            _ => Self::Custom(key.to_owned()),
+        }
+    }
+}
+
+impl serde::Serialize for Status {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.key())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Status {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(Self::from_key(&key))
+    }
+}
+
+/// Names other than the built-in states become custom ones; whether they are
+/// valid for an item is up to its workflow.
+impl std::str::FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "open" => Ok(Self::Open),
+            "inprogress" => Ok(Self::InProgress),
+            "resolved" => Ok(Self::Resolved),
+            "closed" => Ok(Self::Closed),
+            "" => Err("empty status".to_owned()),
+            _ => Ok(Self::Custom(s.trim().to_lowercase())),
+        }
+    }
+}
+
+/// The states of one item type and the transitions allowed between them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Workflow {
+    pub states: Vec<Status>,
+    /// Allowed next states by state; states without an entry may move to
+    /// any other state.
+    pub transitions: HashMap<Status, Vec<Status>>,
+    /// States that count as finished work.
+    pub done: Vec<Status>,
+}
+
+impl Workflow {
+    /// The state new items start out in, the first one of the workflow.
+    pub fn initial(&self) -> &Status {
+        &self.states[0]
+    }
+
+    /// The state an item moves to once work on it starts: the first one
+    /// after the initial state that is not done.
+    pub fn started(&self) -> Option<Status> {
+        self.next_states(self.initial())
+            .into_iter()
+            .find(|state| !self.is_done(state))
+    }
+
+    /// Whether work on an item in `status` is finished.
+    pub fn is_done(&self, status: &Status) -> bool {
+        self.done.contains(status)
+    }
+
+    /// States an item in `from` may move to, in workflow order.
+    pub fn next_states(&self, from: &Status) -> Vec<Status> {
+        self.states
+            .iter()
+            .filter(|state| *state != from)
+            .filter(|state| {
+                self.transitions
+                    .get(from)
+                    .is_none_or(|next| next.contains(state))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Staying in the same state is always allowed.
+    pub fn allows(&self, from: &Status, to: &Status) -> bool {
+        from == to || self.next_states(from).contains(to)
+    }
+}
+
+/// The four built-in states, with every transition allowed.
+impl Default for Workflow {
+    fn default() -> Self {
+        Self {
+            states: vec![
+                Status::Open,
+                Status::InProgress,
+                Status::Resolved,
+                Status::Closed,
+            ],
+            transitions: HashMap::new(),
+            done: vec![Status::Resolved, Status::Closed],
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Workflows {
+    pub epic: Workflow,
+    pub story: Workflow,
+}
+
+/// What finishing an epic does while some of its stories are not done.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum CloseEpicRule {
+    #[default]
+    Allow,
+    /// Refuse with `JiraDatabaseError::InvariantViolation`.
+    Block,
+    /// Move the stories to the epic's new status as well.
+    Cascade,
+}
+
+impl std::str::FromStr for CloseEpicRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "allow" => Ok(Self::Allow),
+            "block" => Ok(Self::Block),
+            "cascade" => Ok(Self::Cascade),
+            _ => Err(format!(
+                "unknown rule '{}', expected allow, block or cascade",
+                s
+            )),
+        }
+    }
+}
+
+/// Consistency rules between epics and their stories.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Invariants {
+    pub close_epic: CloseEpicRule,
+    /// Move an epic in its initial state on to its started state when one
+    /// of its stories starts.
+    pub start_epic_with_story: bool,
+}
+
+/// Declared from most to least urgent, so sorting puts blockers first.
+#[derive(
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum Priority {
+    Blocker,
+    Critical,
+    #[default]
+    Major,
+    Minor,
+    Trivial,
+}
+
+impl std::fmt::Display for Priority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blocker => write!(f, "BLOCKER"),
+            Self::Critical => write!(f, "CRITICAL"),
+            Self::Major => write!(f, "MAJOR"),
+            Self::Minor => write!(f, "MINOR"),
+            Self::Trivial => write!(f, "TRIVIAL"),
+        }
+    }
+}
+
+impl std::str::FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "blocker" => Ok(Self::Blocker),
+            "critical" => Ok(Self::Critical),
+            "major" => Ok(Self::Major),
+            "minor" => Ok(Self::Minor),
+            "trivial" => Ok(Self::Trivial),
+            _ => Err(format!(
+                "unknown priority '{}', expected blocker, critical, major, minor or trivial",
+                s
+            )),
+        }
+    }
+}
+
+/// Order of items in lists; ties are broken by id.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum SortKey {
+    #[default]
+    Id,
+    Priority,
+}
+
+impl SortKey {
+    /// The key after this one, for toggling in the UI.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Id => Self::Priority,
+            Self::Priority => Self::Id,
+        }
+    }
+}
+
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Id => write!(f, "id"),
+            Self::Priority => write!(f, "priority"),
+        }
+    }
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "id" => Ok(Self::Id),
+            "priority" => Ok(Self::Priority),
+            _ => Err(format!("unknown sort key '{}', expected id or priority", s)),
         }
     }
 }
@@ -38,16 +317,73 @@ pub struct Epic {
     pub name: String,
     pub description: String,
     pub status: Status,
+    #[serde(default)]
+    pub priority: Priority,
     pub stories: Vec<u32>,
+    /// Id of the `User` working on the epic.
+    #[serde(default)]
+    pub assignee: Option<u32>,
+    /// Id of the `User` who created the epic.
+    #[serde(default)]
+    pub reporter: Option<u32>,
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+    /// `None` for items saved before timestamps were recorded.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
 }
 
 impl Epic {
     pub fn new(name: String, description: String) -> Self {
+        let now = Utc::now();
         Self {
             name,
             description,
             status: Status::Open,
+            priority: Priority::default(),
             stories: vec![],
+            assignee: None,
+            reporter: None,
+            labels: BTreeSet::new(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            due_date: None,
+        }
+    }
+
+    /// Past its due date on `today` and not yet done in `workflow`.
+    pub fn is_overdue(&self, workflow: &Workflow, today: NaiveDate) -> bool {
+        is_overdue(self.due_date, workflow.is_done(&self.status), today)
+    }
+}
+
+/// Fields of an `Epic` to change; `None` keeps the current value.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct EpicUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub priority: Option<Priority>,
+    /// `Some(None)` clears the due date.
+    pub due_date: Option<Option<NaiveDate>>,
+}
+
+impl EpicUpdate {
+    pub fn apply(self, epic: &mut Epic) {
+        if let Some(name) = self.name {
+            epic.name = name;
+        }
+        if let Some(description) = self.description {
+            epic.description = description;
+        }
+        if let Some(priority) = self.priority {
+            epic.priority = priority;
+        }
+        if let Some(due_date) = self.due_date {
+            epic.due_date = due_date;
         }
     }
 }
@@ -57,21 +393,639 @@ pub struct Story {
     pub name: String,
     pub description: String,
     pub status: Status,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Estimate in story points.
+    #[serde(default)]
+    pub points: Option<u32>,
+    /// Id of the `User` working on the story.
+    #[serde(default)]
+    pub assignee: Option<u32>,
+    /// Id of the `User` who created the story.
+    #[serde(default)]
+    pub reporter: Option<u32>,
+    #[serde(default)]
+    pub labels: BTreeSet<String>,
+    /// `None` for items saved before timestamps were recorded.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    /// Ids of the story's `SubTask`s, in checklist order.
+    #[serde(default)]
+    pub subtasks: Vec<u32>,
 }
 
 impl Story {
     pub fn new(name: String, description: String) -> Self {
+        let now = Utc::now();
         Self {
             name,
             description,
             status: Status::Open,
+            priority: Priority::default(),
+            points: None,
+            assignee: None,
+            reporter: None,
+            labels: BTreeSet::new(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            due_date: None,
+            subtasks: vec![],
         }
     }
+
+    /// Past its due date on `today` and not yet done in `workflow`.
+    pub fn is_overdue(&self, workflow: &Workflow, today: NaiveDate) -> bool {
+        is_overdue(self.due_date, workflow.is_done(&self.status), today)
+    }
+}
+
+/// A step of a story, shown as an item of its checklist.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SubTask {
+    pub name: String,
+    pub description: String,
+    pub status: Status,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SubTask {
+    pub fn new(name: String, description: String) -> Self {
+        let now = Utc::now();
+        Self {
+            name,
+            description,
+            status: Status::Open,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+fn is_overdue(due_date: Option<NaiveDate>, done: bool, today: NaiveDate) -> bool {
+    due_date.is_some_and(|due_date| due_date < today) && !done
+}
+
+/// Fields of a `Story` to change; `None` keeps the current value.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct StoryUpdate {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub priority: Option<Priority>,
+    /// `Some(None)` clears the estimate.
+    pub points: Option<Option<u32>>,
+    /// `Some(None)` clears the due date.
+    pub due_date: Option<Option<NaiveDate>>,
+}
+
+impl StoryUpdate {
+    pub fn apply(self, story: &mut Story) {
+        if let Some(name) = self.name {
+            story.name = name;
+        }
+        if let Some(description) = self.description {
+            story.description = description;
+        }
+        if let Some(priority) = self.priority {
+            story.priority = priority;
+        }
+        if let Some(points) = self.points {
+            story.points = points;
+        }
+        if let Some(due_date) = self.due_date {
+            story.due_date = due_date;
+        }
+    }
+}
+
+/// Labels to add to and remove from an `Epic` or `Story`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LabelUpdate {
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+impl LabelUpdate {
+    /// Parses space-separated `+label` and `-label` words; bare words add.
+    pub fn parse(input: &str) -> Self {
+        let mut update = Self::default();
+        for word in input.split_whitespace() {
+            match word.strip_prefix('-') {
+                Some(label) => update.remove.push(label.to_owned()),
+                None => update
+                    .add
+                    .push(word.strip_prefix('+').unwrap_or(word).to_owned()),
+            }
+        }
+        update
+    }
+
+    /// Labels are single words; commas are reserved for display.
+    pub fn is_valid(&self) -> bool {
+        self.add
+            .iter()
+            .chain(&self.remove)
+            .all(|label| !label.is_empty() && !label.contains([',', ' ']))
+    }
+
+    pub fn apply(self, labels: &mut BTreeSet<String>) {
+        for label in self.remove {
+            labels.remove(&label);
+        }
+        labels.extend(self.add);
+    }
+}
+
+/// The label after `current` among all `label_sets`, for cycling a filter in
+/// the UI; `None`, which shows everything, follows the last one.
+pub fn next_label<'a>(
+    label_sets: impl IntoIterator<Item = &'a BTreeSet<String>>,
+    current: Option<&str>,
+) -> Option<String> {
+    label_sets
+        .into_iter()
+        .flatten()
+        .filter(|label| current.is_none_or(|current| label.as_str() > current))
+        .min()
+        .cloned()
+}
+
+/// Comma-separated labels, for table columns.
+pub fn join_labels(labels: &BTreeSet<String>) -> String {
+    labels.iter().cloned().collect::<Vec<_>>().join(",")
+}
+
+/// Local date and time to the minute, empty when unknown.
+pub fn format_timestamp(timestamp: Option<DateTime<Utc>>) -> String {
+    timestamp.map_or(String::new(), |timestamp| {
+        timestamp
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    })
+}
+
+pub fn format_date(date: Option<NaiveDate>) -> String {
+    date.map_or(String::new(), |date| date.to_string())
+}
+
+/// Story points of an epic's stories; unestimated stories count as zero.
+#[derive(Debug, Default, PartialEq, Clone, Copy, serde::Serialize)]
+pub struct Progress {
+    pub total: u32,
+    /// Points of stories in a done state of the story workflow.
+    pub completed: u32,
+}
+
+impl Progress {
+    pub fn remaining(&self) -> u32 {
+        self.total - self.completed
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct User {
+    /// Unique login name, as set in the `[user]` config section.
+    pub username: String,
+    pub name: String,
+}
+
+impl User {
+    pub fn new(username: String, name: String) -> Self {
+        Self { username, name }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum LinkKind {
+    /// The target cannot be resolved before the source is done.
+    Blocks,
+    RelatesTo,
+    Duplicates,
+}
+
+impl LinkKind {
+    /// How the link reads from its target, e.g. "is blocked by".
+    pub fn inverse(self) -> &'static str {
+        match self {
+            Self::Blocks => "is blocked by",
+            Self::RelatesTo => "relates to",
+            Self::Duplicates => "is duplicated by",
+        }
+    }
+}
+
+impl std::fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Blocks => write!(f, "blocks"),
+            Self::RelatesTo => write!(f, "relates to"),
+            Self::Duplicates => write!(f, "duplicates"),
+        }
+    }
+}
+
+impl std::str::FromStr for LinkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "blocks" => Ok(Self::Blocks),
+            "relates" | "relates-to" | "relates to" => Ok(Self::RelatesTo),
+            "duplicates" => Ok(Self::Duplicates),
+            _ => Err(format!(
+                "unknown link type '{}', expected blocks, relates or duplicates",
+                s
+            )),
+        }
+    }
+}
+
+/// A typed link between two stories, read as "`from` `kind` `to`".
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Link {
+    pub from: u32,
+    pub to: u32,
+    pub kind: LinkKind,
+}
+
+impl Link {
+    pub fn new(from: u32, kind: LinkKind, to: u32) -> Self {
+        Self { from, to, kind }
+    }
+
+    /// The link as seen from `story_id`, e.g. "is blocked by 3".
+    pub fn describe(&self, story_id: u32) -> String {
+        if self.from == story_id {
+            format!("{} {}", self.kind, self.to)
+        } else {
+            format!("{} {}", self.kind.inverse(), self.from)
+        }
+    }
+}
+
+/// A remark on an epic or story.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Comment {
+    /// Id of the commented `Epic` or `Story`.
+    pub item_id: u32,
+    /// Id of the `User` who wrote the comment.
+    pub author: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub body: String,
+}
+
+impl Comment {
+    pub fn new(item_id: u32, author: Option<u32>, body: String) -> Self {
+        Self {
+            item_id,
+            author,
+            created_at: Utc::now(),
+            edited_at: None,
+            body,
+        }
+    }
+}
+
+/// One changed field of an epic or story, recorded by every database write.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AuditEvent {
+    pub at: DateTime<Utc>,
+    /// Username of the session that made the change.
+    pub actor: Option<String>,
+    pub item_id: u32,
+    /// The changed field, or `created`, `deleted`, `restored`, `comment` and `link`.
+    pub field: String,
+    /// Empty when the field had no value.
+    pub old: String,
+    pub new: String,
+}
+
+/// A deleted epic or story, kept until it is restored or purged.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrashEntry {
+    pub deleted_at: DateTime<Utc>,
+    pub item: TrashedItem,
+    /// Comments on the item, and on the stories of a deleted epic.
+    pub comments: HashMap<u32, Comment>,
+    /// Sub-tasks of the deleted stories.
+    #[serde(default)]
+    pub subtasks: HashMap<u32, SubTask>,
+}
+
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub enum TrashedItem {
+    Epic {
+        epic: Epic,
+        stories: HashMap<u32, Story>,
+    },
+    Story {
+        /// The epic the story is restored into.
+        epic_id: u32,
+        story: Story,
+    },
+}
+
+impl TrashEntry {
+    pub fn name(&self) -> &str {
+        match &self.item {
+            TrashedItem::Epic { epic, .. } => &epic.name,
+            TrashedItem::Story { story, .. } => &story.name,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self.item {
+            TrashedItem::Epic { .. } => "epic",
+            TrashedItem::Story { .. } => "story",
+        }
+    }
+}
+
+/// The value of one epic, story, comment or user before and after a write;
+/// `None` when it did not exist.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Change<T> {
+    pub id: u32,
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+/// Everything one database write changed, so that it can be undone and redone.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+    pub actor: Option<String>,
+    pub epics: Vec<Change<Epic>>,
+    pub stories: Vec<Change<Story>>,
+    pub comments: Vec<Change<Comment>>,
+    pub users: Vec<Change<User>>,
+    #[serde(default)]
+    pub trash: Vec<Change<TrashEntry>>,
+    #[serde(default)]
+    pub subtasks: Vec<Change<SubTask>>,
+    #[serde(default)]
+    pub links: Vec<Change<Link>>,
+}
+
+impl JournalEntry {
+    /// Ids of the epics, stories and sub-tasks changed, trashed or restored.
+    pub fn item_ids(&self) -> Vec<u32> {
+        let mut ids = (self.epics.iter().map(|change| change.id))
+            .chain(self.stories.iter().map(|change| change.id))
+            .chain(self.subtasks.iter().map(|change| change.id))
+            .chain(self.trash.iter().map(|change| change.id))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DBState {
+    #[serde(default)]
+    pub schema_version: u32,
+    /// Id sequence of epics, stories and sub-tasks.
     pub last_item_id: u32,
+    /// Id sequences of users, comments and links, kept apart so that epic
+    /// and story numbers have no gaps.
+    #[serde(default)]
+    pub last_user_id: u32,
+    #[serde(default)]
+    pub last_comment_id: u32,
+    #[serde(default)]
+    pub last_link_id: u32,
+    #[serde(default)]
+    pub revision: u64,
     pub epics: HashMap<u32, Epic>,
     pub stories: HashMap<u32, Story>,
+    /// Sub-tasks share the id sequence of epics and stories.
+    #[serde(default)]
+    pub subtasks: HashMap<u32, SubTask>,
+    #[serde(default)]
+    pub users: HashMap<u32, User>,
+    #[serde(default)]
+    pub comments: HashMap<u32, Comment>,
+    /// Links between stories. They outlive deleted stories until those are purged from the trash.
+    #[serde(default)]
+    pub links: HashMap<u32, Link>,
+    /// Append-only, oldest first.
+    #[serde(default)]
+    pub history: Vec<AuditEvent>,
+    /// Writes that can be undone, oldest first.
+    #[serde(default)]
+    pub undo: Vec<JournalEntry>,
+    /// Undone writes that can be redone, oldest first.
+    #[serde(default)]
+    pub redo: Vec<JournalEntry>,
+    /// Deleted epics and stories by id.
+    #[serde(default)]
+    pub trash: HashMap<u32, TrashEntry>,
+}
+
+impl DBState {
+    /// An empty database at the current schema version.
+    pub fn new() -> Self {
+        Self {
+            schema_version: crate::db::CURRENT_SCHEMA_VERSION,
+            last_item_id: 0,
+            last_user_id: 0,
+            last_comment_id: 0,
+            last_link_id: 0,
+            revision: 0,
+            epics: HashMap::new(),
+            stories: HashMap::new(),
+            subtasks: HashMap::new(),
+            users: HashMap::new(),
+            comments: HashMap::new(),
+            links: HashMap::new(),
+            history: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            trash: HashMap::new(),
+        }
+    }
+
+    pub fn find_user(&self, username: &str) -> Option<u32> {
+        self.users
+            .iter()
+            .find(|(_, user)| user.username == username)
+            .map(|(id, _)| *id)
+    }
+
+    /// Rolls up the points of the stories in `epic`, counting those done in
+    /// the story `workflow` as completed.
+    pub fn progress(&self, epic: &Epic, workflow: &Workflow) -> Progress {
+        epic.stories
+            .iter()
+            .filter_map(|id| self.stories.get(id))
+            .fold(Progress::default(), |mut progress, story| {
+                let points = story.points.unwrap_or(0);
+                progress.total = progress.total.saturating_add(points);
+                if workflow.is_done(&story.status) {
+                    progress.completed = progress.completed.saturating_add(points);
+                }
+                progress
+            })
+    }
+
+    /// Ids of all epics, in `sort` order.
+    pub fn sorted_epic_ids(&self, sort: SortKey) -> Vec<u32> {
+        let mut ids = self.epics.keys().copied().collect::<Vec<_>>();
+        match sort {
+            SortKey::Id => ids.sort(),
+            SortKey::Priority => ids.sort_by_key(|id| (self.epics[id].priority, *id)),
+        }
+        ids
+    }
+
+    /// `ids` of existing stories, in `sort` order.
+    pub fn sorted_story_ids<'a>(
+        &self,
+        ids: impl IntoIterator<Item = &'a u32>,
+        sort: SortKey,
+    ) -> Vec<u32> {
+        let mut ids = ids
+            .into_iter()
+            .copied()
+            .filter(|id| self.stories.contains_key(id))
+            .collect::<Vec<_>>();
+        match sort {
+            SortKey::Id => ids.sort(),
+            SortKey::Priority => ids.sort_by_key(|id| (self.stories[id].priority, *id)),
+        }
+        ids
+    }
+
+    /// Sub-tasks of `story` in checklist order.
+    pub fn checklist(&self, story: &Story) -> Vec<(u32, &SubTask)> {
+        story
+            .subtasks
+            .iter()
+            .filter_map(|id| self.subtasks.get(id).map(|subtask| (*id, subtask)))
+            .collect()
+    }
+
+    /// Comments on `item_id`, oldest first.
+    pub fn comments_on(&self, item_id: u32) -> Vec<(u32, &Comment)> {
+        let mut comments = self
+            .comments
+            .iter()
+            .filter(|(_, comment)| comment.item_id == item_id)
+            .map(|(id, comment)| (*id, comment))
+            .collect::<Vec<_>>();
+        comments.sort_by_key(|(id, _)| *id);
+        comments
+    }
+
+    /// Links from and to `story_id` whose other story exists, oldest first.
+    pub fn links_of(&self, story_id: u32) -> Vec<(u32, &Link)> {
+        let mut links = self
+            .links
+            .iter()
+            .filter(|(_, link)| {
+                (link.from == story_id && self.stories.contains_key(&link.to))
+                    || (link.to == story_id && self.stories.contains_key(&link.from))
+            })
+            .map(|(id, link)| (*id, link))
+            .collect::<Vec<_>>();
+        links.sort_by_key(|(id, _)| *id);
+        links
+    }
+
+    /// Ids of the stories blocking `story_id` that are not done yet in the
+    /// story `workflow`.
+    pub fn open_blockers(&self, story_id: u32, workflow: &Workflow) -> Vec<u32> {
+        let mut blockers = self
+            .links
+            .values()
+            .filter(|link| link.kind == LinkKind::Blocks && link.to == story_id)
+            .map(|link| link.from)
+            .filter(|id| {
+                self.stories
+                    .get(id)
+                    .is_some_and(|story| !workflow.is_done(&story.status))
+            })
+            .collect::<Vec<_>>();
+        blockers.sort();
+        blockers.dedup();
+        blockers
+    }
+
+    /// Whether `id` is a trashed epic or story, or a story or sub-task
+    /// deleted along with one.
+    pub fn is_trashed(&self, id: u32) -> bool {
+        self.trash.contains_key(&id)
+            || self.trash.values().any(|entry| {
+                entry.subtasks.contains_key(&id)
+                    || matches!(&entry.item, TrashedItem::Epic { stories, .. } if stories.contains_key(&id))
+            })
+    }
+
+    /// Audit events of `item_id`, oldest first.
+    pub fn history_of(&self, item_id: u32) -> Vec<&AuditEvent> {
+        self.history
+            .iter()
+            .filter(|event| event.item_id == item_id)
+            .collect()
+    }
+
+    /// Trashed epics and stories, most recently deleted first.
+    pub fn trashed(&self) -> Vec<(u32, &TrashEntry)> {
+        let mut entries = self
+            .trash
+            .iter()
+            .map(|(id, entry)| (*id, entry))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(id, entry)| (std::cmp::Reverse(entry.deleted_at), *id));
+        entries
+    }
+
+    /// Username of `user_id`, or an empty string for unassigned items.
+    pub fn username(&self, user_id: Option<u32>) -> &str {
+        user_id
+            .and_then(|id| self.users.get(&id))
+            .map_or("", |user| user.username.as_str())
+    }
+
+    /// A database holding one sample epic with two stories.
+    pub fn sample() -> Self {
+        let mut db_state = Self::new();
+
+        let mut epic = Epic::new(
+            "Epic - Project 1".to_owned(),
+            "This is Project 1 for the Bootcamp".to_owned(),
+        );
+        epic.stories = vec![2, 3];
+        db_state.epics.insert(1, epic);
+        db_state.stories.insert(
+            2,
+            Story::new(
+                "Story - Project 1 Solution".to_owned(),
+                "Please provide full implement for Project 1".to_owned(),
+            ),
+        );
+        db_state.stories.insert(
+            3,
+            Story::new(
+                "Story - Project 1 README".to_owned(),
+                "Please create README file for Project 1".to_owned(),
+            ),
+        );
+        db_state.last_item_id = 3;
+
+        db_state
+    }
+}
+
+impl Default for DBState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
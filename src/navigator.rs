@@ -1,9 +1,9 @@
-use error_stack::{Result, ResultExt};
+use error_stack::{IntoReport, Report, Result, ResultExt};
 
 use std::rc::Rc;
 
 use crate::{
-    db::JiraDatabase,
+    db::{JiraDatabase, JiraDatabaseError, Sections},
     models::Action,
     ui::{Page, Prompts},
 };
@@ -36,27 +36,77 @@ pub struct Navigator {
     pages: Vec<Box<dyn Page>>,
     prompts: Prompts,
     db: Rc<JiraDatabase>,
+    current_user: Option<String>,
 }
 
 impl Navigator {
     pub fn new(db: Rc<JiraDatabase>) -> Self {
+        Self::with_prompts(db, crate::ui::Prompts::new())
+    }
+
+    pub fn with_prompts(db: Rc<JiraDatabase>, prompts: Prompts) -> Self {
         Navigator {
-            pages: vec![std::boxed::Box::new(crate::ui::HomePage { db: db.clone() })],
-            prompts: crate::ui::Prompts::new(),
+            pages: vec![std::boxed::Box::new(crate::ui::HomePage {
+                db: db.clone(),
+                sort: Default::default(),
+                label: Default::default(),
+            })],
+            prompts,
             db,
+            current_user: None,
         }
     }
 
+    /// Sets who runs the tracker, see `Config::current_user`.
+    pub fn with_current_user(mut self, username: Option<String>) -> Self {
+        self.current_user = username;
+        self
+    }
+
+    /// Id of the current user, registering it in the user directory first.
+    fn current_user_id(&self) -> Result<Option<u32>, NavigationError> {
+        self.current_user
+            .as_deref()
+            .map(|username| self.db.ensure_user(username))
+            .transpose()
+            .change_context(NavigationError::Create)
+    }
+
     pub fn get_current_page(&self) -> Option<&dyn Page> {
         self.pages.last().map(|p| p.as_ref())
     }
 
+    /// Performs `action`, whose writes are undone together.
     pub fn handle_action(&mut self, action: Action) -> Result<(), NavigationError> {
+        let db = self.db.clone();
+        match db.in_action(|| self.perform_action(action.clone())) {
+            Err(e) if Self::is_conflict(&e) => {
+                if (self.prompts.reload_and_retry)() {
+                    db.read_db().change_context(NavigationError::Update)?;
+                    db.in_action(|| self.perform_action(action))
+                } else {
+                    Ok(())
+                }
+            }
+            result => result,
+        }
+    }
+
+    fn is_conflict(report: &Report<NavigationError>) -> bool {
+        matches!(
+            report.downcast_ref::<JiraDatabaseError>(),
+            Some(JiraDatabaseError::Conflict)
+        )
+    }
+
+    fn perform_action(&mut self, action: Action) -> Result<(), NavigationError> {
         match action {
             Action::NavigateToEpicDetail { epic_id } => {
                 self.pages.push(std::boxed::Box::new(crate::ui::EpicDetail {
                     epic_id,
                     db: self.db.clone(),
+                    sort: Default::default(),
+                    label: Default::default(),
                 }))
             }
             Action::NavigateToStoryDetail { epic_id, story_id } => {
@@ -67,20 +117,58 @@ impl Navigator {
                         db: self.db.clone(),
                     }))
             }
+            Action::NavigateToSubTaskDetail {
+                story_id,
+                subtask_id,
+            } => self
+                .pages
+                .push(std::boxed::Box::new(crate::ui::SubTaskDetail {
+                    story_id,
+                    subtask_id,
+                    db: self.db.clone(),
+                })),
             Action::NavigateToPreviousPage => {
                 self.pages.pop();
             }
             Action::CreateEpic => {
-                let epic = (self.prompts.create_epic)();
+                let Some(mut epic) = (self.prompts.create_epic)() else {
+                    return Ok(());
+                };
+                epic.reporter = self.current_user_id()?;
                 let _ = self
                     .db
                     .create_epic(epic)
                     .change_context(NavigationError::Create)?;
             }
             Action::UpdateEpicStatus { epic_id } => {
-                let status = (self.prompts.update_status)().ok_or(NavigationError::Update)?;
+                let next_states = self
+                    .db
+                    .next_states(epic_id)
+                    .change_context(NavigationError::Update)?;
+                let status =
+                    (self.prompts.update_status)(&next_states).ok_or(NavigationError::Update)?;
+                match self.db.update_epic_status(epic_id, status.clone()) {
+                    // Closing is blocked by unfinished stories, offer to close them too.
+                    Err(e)
+                        if matches!(e.current_context(), JiraDatabaseError::InvariantViolation)
+                            && (self.prompts.close_stories)() =>
+                    {
+                        self.db
+                            .update_epic_status_cascading(epic_id, status)
+                            .change_context(NavigationError::Update)?
+                    }
+                    result => result.change_context(NavigationError::Update)?,
+                }
+            }
+            Action::EditEpic { epic_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                let epic = db_state
+                    .epics
+                    .get(&epic_id)
+                    .ok_or(NavigationError::Update)?;
+                let update = (self.prompts.edit_epic)(epic);
                 self.db
-                    .update_epic_status(epic_id, status)
+                    .update_epic(epic_id, update)
                     .change_context(NavigationError::Update)?
             }
             Action::DeleteEpic { epic_id } => {
@@ -91,18 +179,37 @@ impl Navigator {
                 }
             }
             Action::CreateStory { epic_id } => {
-                let story = (self.prompts.create_story)();
+                let Some(mut story) = (self.prompts.create_story)() else {
+                    return Ok(());
+                };
+                story.reporter = self.current_user_id()?;
                 let _ = self
                     .db
                     .create_story(story, epic_id)
                     .change_context(NavigationError::Create)?;
             }
             Action::UpdateStoryStatus { story_id } => {
-                let status = (self.prompts.update_status)().ok_or(NavigationError::Update)?;
+                let next_states = self
+                    .db
+                    .next_states(story_id)
+                    .change_context(NavigationError::Update)?;
+                let status =
+                    (self.prompts.update_status)(&next_states).ok_or(NavigationError::Update)?;
                 self.db
                     .update_story_status(story_id, status)
                     .change_context(NavigationError::Update)?
             }
+            Action::EditStory { story_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                let story = db_state
+                    .stories
+                    .get(&story_id)
+                    .ok_or(NavigationError::Update)?;
+                let update = (self.prompts.edit_story)(story);
+                self.db
+                    .update_story(story_id, update)
+                    .change_context(NavigationError::Update)?
+            }
             Action::DeleteStory { epic_id, story_id } => {
                 if (self.prompts.delete_story)() {
                     self.db
@@ -110,12 +217,199 @@ impl Navigator {
                         .change_context(NavigationError::Delete)?;
                 }
             }
+            Action::CreateSubTask { story_id } => {
+                let Some(subtask) = (self.prompts.create_subtask)() else {
+                    return Ok(());
+                };
+                let _ = self
+                    .db
+                    .create_subtask(subtask, story_id)
+                    .change_context(NavigationError::Create)?;
+            }
+            Action::UpdateSubTaskStatus { subtask_id } => {
+                let next_states = self
+                    .db
+                    .next_states(subtask_id)
+                    .change_context(NavigationError::Update)?;
+                let status =
+                    (self.prompts.update_status)(&next_states).ok_or(NavigationError::Update)?;
+                self.db
+                    .update_subtask_status(subtask_id, status)
+                    .change_context(NavigationError::Update)?
+            }
+            Action::DeleteSubTask {
+                story_id,
+                subtask_id,
+            } => {
+                if (self.prompts.delete_subtask)() {
+                    self.db
+                        .delete_subtask(story_id, subtask_id)
+                        .change_context(NavigationError::Delete)?;
+                    self.drop_stale_pages()?;
+                }
+            }
+            Action::AddStoryLink { story_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Create)?;
+                if let Some(link) = (self.prompts.link)(&db_state, story_id) {
+                    self.db
+                        .add_link(link)
+                        .change_context(NavigationError::Create)?;
+                }
+            }
+            Action::RemoveStoryLink { story_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Delete)?;
+                if let Some(link_id) = (self.prompts.unlink)(&db_state, story_id) {
+                    self.db
+                        .remove_link(link_id)
+                        .change_context(NavigationError::Delete)?;
+                }
+            }
+            Action::MoveStory { epic_id, story_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                let Some(to_epic) = (self.prompts.move_story)(&db_state, epic_id) else {
+                    return Ok(());
+                };
+                self.db
+                    .move_story(story_id, epic_id, to_epic)
+                    .change_context(NavigationError::Update)?;
+
+                // The story and its old epic are on top of the stack, show it
+                // under its new epic instead.
+                self.pages.pop();
+                self.pages.pop();
+                self.pages.push(std::boxed::Box::new(crate::ui::EpicDetail {
+                    epic_id: to_epic,
+                    db: self.db.clone(),
+                    sort: Default::default(),
+                    label: Default::default(),
+                }));
+                self.pages
+                    .push(std::boxed::Box::new(crate::ui::StoryDetail {
+                        epic_id: to_epic,
+                        story_id,
+                        db: self.db.clone(),
+                    }));
+            }
+            Action::AssignEpic { epic_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                if let Some(assignee) = (self.prompts.assign)(&db_state) {
+                    self.db
+                        .assign_epic(epic_id, assignee)
+                        .change_context(NavigationError::Update)?;
+                }
+            }
+            Action::AssignStory { story_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                if let Some(assignee) = (self.prompts.assign)(&db_state) {
+                    self.db
+                        .assign_story(story_id, assignee)
+                        .change_context(NavigationError::Update)?;
+                }
+            }
+            Action::EditEpicLabels { epic_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                let epic = db_state
+                    .epics
+                    .get(&epic_id)
+                    .ok_or(NavigationError::Update)?;
+                let update = (self.prompts.edit_labels)(&epic.labels);
+                self.db
+                    .update_epic_labels(epic_id, update)
+                    .change_context(NavigationError::Update)?
+            }
+            Action::EditStoryLabels { story_id } => {
+                let db_state = self.db.read_db().change_context(NavigationError::Update)?;
+                let story = db_state
+                    .stories
+                    .get(&story_id)
+                    .ok_or(NavigationError::Update)?;
+                let update = (self.prompts.edit_labels)(&story.labels);
+                self.db
+                    .update_story_labels(story_id, update)
+                    .change_context(NavigationError::Update)?
+            }
+            Action::AddComment { item_id } => {
+                if let Some(body) = (self.prompts.new_comment)() {
+                    let author = self.current_user_id()?;
+                    self.db
+                        .add_comment(item_id, author, body)
+                        .change_context(NavigationError::Create)?;
+                }
+            }
+            Action::NavigateToHistory { item_id } => {
+                self.pages.push(std::boxed::Box::new(crate::ui::History {
+                    item_id,
+                    db: self.db.clone(),
+                }));
+            }
+            Action::NavigateToAssignedToMe => {
+                let username = self
+                    .current_user
+                    .clone()
+                    .ok_or(NavigationError::Navigation)
+                    .into_report()
+                    .attach_printable("Set `username` in the `[user]` config section.")?;
+                self.pages
+                    .push(std::boxed::Box::new(crate::ui::AssignedToMe {
+                        username,
+                        db: self.db.clone(),
+                    }));
+            }
+            Action::NavigateToTrash => {
+                self.pages.push(std::boxed::Box::new(crate::ui::Trash {
+                    db: self.db.clone(),
+                }));
+            }
+            Action::RestoreFromTrash => {
+                let db_state = self
+                    .db
+                    .read_db_with(Sections::TRASH)
+                    .change_context(NavigationError::Recover)?;
+                if let Some(item_id) = (self.prompts.restore)(&db_state) {
+                    self.db
+                        .restore(item_id)
+                        .change_context(NavigationError::Recover)?;
+                }
+            }
+            Action::Undo => {
+                self.db.undo().change_context(NavigationError::Update)?;
+                self.drop_stale_pages()?;
+            }
+            Action::Redo => {
+                self.db.redo().change_context(NavigationError::Update)?;
+                self.drop_stale_pages()?;
+            }
             Action::Exit => self.pages.clear(),
         }
 
         Ok(())
     }
 
+    /// Pops the first page showing an epic, story or sub-task that no longer
+    /// exists, with every page above it.
+    fn drop_stale_pages(&mut self) -> Result<(), NavigationError> {
+        let db_state = self
+            .db
+            .read_db()
+            .change_context(NavigationError::Navigation)?;
+        let stale = self.pages.iter().position(|page| {
+            let page = page.as_any();
+            if let Some(epic_detail) = page.downcast_ref::<crate::ui::EpicDetail>() {
+                !db_state.epics.contains_key(&epic_detail.epic_id)
+            } else if let Some(story_detail) = page.downcast_ref::<crate::ui::StoryDetail>() {
+                !db_state.stories.contains_key(&story_detail.story_id)
+            } else if let Some(subtask_detail) = page.downcast_ref::<crate::ui::SubTaskDetail>() {
+                !db_state.subtasks.contains_key(&subtask_detail.subtask_id)
+            } else {
+                false
+            }
+        });
+        if let Some(index) = stale {
+            self.pages.truncate(index);
+        }
+        Ok(())
+    }
+
     // Private functions used for testing
 
     #[cfg(test)]
@@ -134,15 +428,16 @@ mod tests {
     use super::*;
     use crate::{
         db::test_utils::MockDB,
-        models::{Epic, Status, Story},
-        ui::{EpicDetail, HomePage, StoryDetail},
+        models::{
+            CloseEpicRule, Epic, EpicUpdate, Invariants, LabelUpdate, Link, LinkKind, Priority,
+            Status, Story, StoryUpdate, SubTask, Workflow, Workflows,
+        },
+        ui::{AssignedToMe, EpicDetail, HomePage, StoryDetail, SubTaskDetail, Trash},
     };
 
     #[test]
     fn should_start_on_home_page() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
-        });
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
         let nav = Navigator::new(db);
 
         assert_eq!(nav.get_page_count(), 1);
@@ -155,9 +450,7 @@ mod tests {
 
     #[test]
     fn handle_action_should_navigate_pages() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
-        });
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
         let mut nav = Navigator::new(db);
 
@@ -203,9 +496,7 @@ mod tests {
 
     #[test]
     fn handle_action_should_clear_pages_on_exit() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
-        });
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
         let mut nav = Navigator::new(db);
 
@@ -223,14 +514,13 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_create_epic() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
-        });
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
         let mut nav = Navigator::new(Rc::clone(&db));
 
         let mut prompts = Prompts::new();
-        prompts.create_epic = Box::new(|| Epic::new("name".to_owned(), "description".to_owned()));
+        prompts.create_epic =
+            Box::new(|| Some(Epic::new("name".to_owned(), "description".to_owned())));
 
         nav.set_prompts(prompts);
 
@@ -246,9 +536,7 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_update_epic() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
-        });
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
         let epic_id = db
             .create_epic(Epic::new("".to_owned(), "".to_owned()))
             .unwrap();
@@ -256,7 +544,7 @@ mod tests {
         let mut nav = Navigator::new(Rc::clone(&db));
 
         let mut prompts = Prompts::new();
-        prompts.update_status = Box::new(|| Some(Status::InProgress));
+        prompts.update_status = Box::new(|_| Some(Status::InProgress));
 
         nav.set_prompts(prompts);
 
@@ -271,10 +559,36 @@ mod tests {
     }
 
     #[test]
-    fn handle_action_should_handle_delete_epic() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
+    fn handle_action_should_handle_edit_epic() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("name".to_owned(), "description".to_owned()))
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.edit_epic = Box::new(|epic| EpicUpdate {
+            name: Some(format!("{} 2", epic.name)),
+            description: None,
+            priority: Some(Priority::Critical),
+            due_date: None,
         });
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::EditEpic { epic_id }).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let epic = db_state.epics.get(&epic_id).unwrap();
+        assert_eq!(epic.name, "name 2");
+        assert_eq!(epic.description, "description");
+        assert_eq!(epic.priority, Priority::Critical);
+    }
+
+    #[test]
+    fn handle_action_should_handle_delete_epic() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
         let epic_id = db
             .create_epic(Epic::new("".to_owned(), "".to_owned()))
             .unwrap();
@@ -294,9 +608,7 @@ mod tests {
 
     #[test]
     fn handle_action_should_handle_create_story() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
-        });
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
         let epic_id = db
             .create_epic(Epic::new("".to_owned(), "".to_owned()))
             .unwrap();
@@ -304,7 +616,8 @@ mod tests {
         let mut nav = Navigator::new(Rc::clone(&db));
 
         let mut prompts = Prompts::new();
-        prompts.create_story = Box::new(|| Story::new("name".to_owned(), "description".to_owned()));
+        prompts.create_story =
+            Box::new(|| Some(Story::new("name".to_owned(), "description".to_owned())));
 
         nav.set_prompts(prompts);
 
@@ -319,10 +632,49 @@ mod tests {
     }
 
     #[test]
-    fn handle_action_should_handle_update_story() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
+    fn handle_action_should_offer_next_states_of_the_workflow() {
+        let story_workflow = Workflow {
+            transitions: [(Status::Open, vec![Status::InProgress, Status::Closed])].into(),
+            ..Workflow::default()
+        };
+        let db = Rc::new(
+            JiraDatabase::with_database(Box::new(MockDB::new())).with_workflows(Workflows {
+                epic: Workflow::default(),
+                story: story_workflow,
+            }),
+        );
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|next_states| {
+            assert_eq!(next_states, [Status::InProgress, Status::Closed]);
+            Some(Status::Closed)
         });
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::UpdateStoryStatus { story_id })
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.stories[&story_id].status, Status::Closed);
+    }
+
+    #[test]
+    fn handle_action_should_offer_to_close_stories_with_their_epic() {
+        let db = Rc::new(
+            JiraDatabase::with_database(Box::new(MockDB::new())).with_invariants(Invariants {
+                close_epic: CloseEpicRule::Block,
+                ..Default::default()
+            }),
+        );
         let epic_id = db
             .create_epic(Epic::new("".to_owned(), "".to_owned()))
             .unwrap();
@@ -333,7 +685,42 @@ mod tests {
         let mut nav = Navigator::new(Rc::clone(&db));
 
         let mut prompts = Prompts::new();
-        prompts.update_status = Box::new(|| Some(Status::InProgress));
+        prompts.update_status = Box::new(|_| Some(Status::Closed));
+        prompts.close_stories = Box::new(|| false);
+        nav.set_prompts(prompts);
+
+        assert!(nav
+            .handle_action(Action::UpdateEpicStatus { epic_id })
+            .is_err());
+        assert_eq!(db.read_db().unwrap().epics[&epic_id].status, Status::Open);
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|_| Some(Status::Closed));
+        prompts.close_stories = Box::new(|| true);
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::UpdateEpicStatus { epic_id })
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics[&epic_id].status, Status::Closed);
+        assert_eq!(db_state.stories[&story_id].status, Status::Closed);
+    }
+
+    #[test]
+    fn handle_action_should_handle_update_story() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|_| Some(Status::InProgress));
 
         nav.set_prompts(prompts);
 
@@ -348,10 +735,42 @@ mod tests {
     }
 
     #[test]
-    fn handle_action_should_handle_delete_story() {
-        let db = Rc::new(JiraDatabase {
-            database: Box::new(MockDB::new()),
+    fn handle_action_should_handle_edit_story() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(
+                Story::new("name".to_owned(), "description".to_owned()),
+                epic_id,
+            )
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.edit_story = Box::new(|_| StoryUpdate {
+            name: None,
+            description: Some("new description".to_owned()),
+            priority: None,
+            points: None,
+            due_date: None,
         });
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::EditStory { story_id }).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let story = db_state.stories.get(&story_id).unwrap();
+        assert_eq!(story.name, "name");
+        assert_eq!(story.description, "new description");
+    }
+
+    #[test]
+    fn handle_action_should_handle_delete_story() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
         let epic_id = db
             .create_epic(Epic::new("".to_owned(), "".to_owned()))
             .unwrap();
@@ -372,4 +791,372 @@ mod tests {
         let db_state = db.read_db().unwrap();
         assert_eq!(db_state.stories.len(), 0);
     }
+
+    #[test]
+    fn handle_action_should_handle_move_story() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let from_epic = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let to_epic = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), from_epic)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.move_story = Box::new(move |_, _| Some(to_epic));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::NavigateToEpicDetail { epic_id: from_epic })
+            .unwrap();
+        nav.handle_action(Action::NavigateToStoryDetail {
+            epic_id: from_epic,
+            story_id,
+        })
+        .unwrap();
+        nav.handle_action(Action::MoveStory {
+            epic_id: from_epic,
+            story_id,
+        })
+        .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics[&from_epic].stories.is_empty());
+        assert_eq!(db_state.epics[&to_epic].stories, vec![story_id]);
+
+        assert_eq!(nav.get_page_count(), 3);
+        let story_detail = nav
+            .get_current_page()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StoryDetail>()
+            .unwrap();
+        assert_eq!(story_detail.epic_id, to_epic);
+    }
+
+    #[test]
+    fn handle_action_should_set_reporter_and_assignee() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+
+        let mut nav = Navigator::new(Rc::clone(&db)).with_current_user(Some("jdoe".to_owned()));
+
+        let mut prompts = Prompts::new();
+        prompts.create_epic = Box::new(|| Some(Epic::new("".to_owned(), "".to_owned())));
+        prompts.assign = Box::new(|db_state| Some(db_state.find_user("jdoe")));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateEpic).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let user_id = db_state.find_user("jdoe");
+        let (epic_id, epic) = db_state.epics.iter().next().unwrap();
+        assert!(user_id.is_some());
+        assert_eq!(epic.reporter, user_id);
+        assert_eq!(epic.assignee, None);
+
+        nav.handle_action(Action::AssignEpic { epic_id: *epic_id })
+            .unwrap();
+        assert_eq!(db.read_db().unwrap().epics[epic_id].assignee, user_id);
+
+        nav.handle_action(Action::NavigateToAssignedToMe).unwrap();
+        assert!(nav
+            .get_current_page()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<AssignedToMe>()
+            .is_some());
+    }
+
+    #[test]
+    fn handle_action_should_handle_edit_labels() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.edit_labels = Box::new(|_| LabelUpdate::parse("backend +urgent"));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::EditEpicLabels { epic_id })
+            .unwrap();
+        nav.handle_action(Action::EditStoryLabels { story_id })
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let expected = ["backend", "urgent"];
+        assert!(db_state.epics[&epic_id].labels.iter().eq(expected.iter()));
+        assert!(db_state.stories[&story_id]
+            .labels
+            .iter()
+            .eq(expected.iter()));
+    }
+
+    #[test]
+    fn handle_action_should_handle_add_comment() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db)).with_current_user(Some("jdoe".to_owned()));
+
+        let mut prompts = Prompts::new();
+        prompts.new_comment = Box::new(|| Some("Looks good".to_owned()));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::AddComment { item_id: story_id })
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        let comments = db_state.comments_on(story_id);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].1.body, "Looks good");
+        assert_eq!(comments[0].1.author, db_state.find_user("jdoe"));
+    }
+
+    #[test]
+    fn handle_action_should_handle_subtasks() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.create_subtask = Box::new(|| Some(SubTask::new("docs".to_owned(), "".to_owned())));
+        prompts.update_status = Box::new(|next_states| next_states.last().cloned());
+        prompts.delete_subtask = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::CreateSubTask { story_id })
+            .unwrap();
+        let subtask_id = db.read_db().unwrap().stories[&story_id].subtasks[0];
+
+        nav.handle_action(Action::NavigateToStoryDetail { epic_id, story_id })
+            .unwrap();
+        nav.handle_action(Action::NavigateToSubTaskDetail {
+            story_id,
+            subtask_id,
+        })
+        .unwrap();
+        assert!(nav
+            .get_current_page()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<SubTaskDetail>()
+            .is_some());
+
+        nav.handle_action(Action::UpdateSubTaskStatus { subtask_id })
+            .unwrap();
+        assert_eq!(
+            db.read_db().unwrap().subtasks[&subtask_id].status,
+            Status::Closed
+        );
+
+        nav.handle_action(Action::DeleteSubTask {
+            story_id,
+            subtask_id,
+        })
+        .unwrap();
+        assert!(db.read_db().unwrap().subtasks.is_empty());
+        assert!(nav
+            .get_current_page()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<StoryDetail>()
+            .is_some());
+    }
+
+    #[test]
+    fn handle_action_should_handle_story_links() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        let other_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.link =
+            Box::new(move |_, story_id| Some(Link::new(other_id, LinkKind::Blocks, story_id)));
+        prompts.unlink =
+            Box::new(|db_state, story_id| db_state.links_of(story_id).first().map(|(id, _)| *id));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::AddStoryLink { story_id })
+            .unwrap();
+        assert_eq!(
+            db.read_db()
+                .unwrap()
+                .open_blockers(story_id, &db.workflows.story),
+            vec![other_id]
+        );
+        assert!(nav
+            .handle_action(Action::AddStoryLink { story_id })
+            .is_err());
+
+        nav.handle_action(Action::RemoveStoryLink { story_id })
+            .unwrap();
+        assert!(db.read_db().unwrap().links.is_empty());
+    }
+
+    #[test]
+    fn handle_action_should_handle_undo_and_redo() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.delete_epic = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::DeleteEpic { epic_id }).unwrap();
+        nav.handle_action(Action::Undo).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.epics.contains_key(&epic_id));
+        assert!(db_state.stories.contains_key(&story_id));
+
+        nav.handle_action(Action::NavigateToEpicDetail { epic_id })
+            .unwrap();
+        nav.handle_action(Action::NavigateToStoryDetail { epic_id, story_id })
+            .unwrap();
+        nav.handle_action(Action::Redo).unwrap();
+
+        assert!(db.read_db().unwrap().epics.is_empty());
+        assert_eq!(nav.get_page_count(), 1);
+        assert!(nav.handle_action(Action::Redo).is_err());
+    }
+
+    #[test]
+    fn handle_action_should_handle_restore_from_trash() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+        let story_id = db
+            .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+            .unwrap();
+        db.delete_story(epic_id, story_id).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.restore = Box::new(move |_| Some(story_id));
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::NavigateToTrash).unwrap();
+        assert!(nav
+            .get_current_page()
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Trash>()
+            .is_some());
+
+        nav.handle_action(Action::RestoreFromTrash).unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert!(db_state.trash.is_empty());
+        assert_eq!(db_state.epics[&epic_id].stories, vec![story_id]);
+        assert!(nav.handle_action(Action::RestoreFromTrash).is_err());
+    }
+
+    #[test]
+    fn handle_action_should_error_for_assigned_to_me_without_user() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let mut nav = Navigator::new(db);
+
+        assert!(nav.handle_action(Action::NavigateToAssignedToMe).is_err());
+        assert_eq!(nav.get_page_count(), 1);
+    }
+
+    #[test]
+    fn handle_action_should_reload_and_retry_on_conflict() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+
+        // Another session bumps the revision behind this one's back.
+        let mut db_state = db.database.read_db().unwrap();
+        db_state.revision += 1;
+        db.database.write_db(&db_state).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|_| Some(Status::Closed));
+        prompts.reload_and_retry = Box::new(|| true);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::UpdateEpicStatus { epic_id })
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics.get(&epic_id).unwrap().status, Status::Closed);
+    }
+
+    #[test]
+    fn handle_action_should_not_write_on_conflict_without_retry() {
+        let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+        let epic_id = db
+            .create_epic(Epic::new("".to_owned(), "".to_owned()))
+            .unwrap();
+
+        let mut db_state = db.database.read_db().unwrap();
+        db_state.revision += 1;
+        db.database.write_db(&db_state).unwrap();
+
+        let mut nav = Navigator::new(Rc::clone(&db));
+
+        let mut prompts = Prompts::new();
+        prompts.update_status = Box::new(|_| Some(Status::Closed));
+        prompts.reload_and_retry = Box::new(|| false);
+
+        nav.set_prompts(prompts);
+
+        nav.handle_action(Action::UpdateEpicStatus { epic_id })
+            .unwrap();
+
+        let db_state = db.read_db().unwrap();
+        assert_eq!(db_state.epics.get(&epic_id).unwrap().status, Status::Open);
+    }
 }
@@ -0,0 +1,160 @@
+use std::io::Write;
+use std::process::Command;
+
+use error_stack::{IntoReport, Result, ResultExt};
+
+#[derive(Debug)]
+pub enum EditorError {
+    Launch,
+    Read,
+    Aborted,
+}
+
+impl std::fmt::Display for EditorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let m = match self {
+            EditorError::Launch => "Failed to launch editor.",
+            EditorError::Read => "Failed to read edited file.",
+            EditorError::Aborted => "Editor was closed without content, aborting.",
+        };
+        write!(f, "{}", m)
+    }
+}
+
+impl std::error::Error for EditorError {}
+
+const TEMPLATE_HEADER: &str = "\
+# These help lines are ignored; '#' lines below them are kept.
+# Save an empty file to abort.
+";
+
+/// Name and description of an item, as written in the editor template.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ItemFields {
+    pub name: String,
+    pub description: String,
+}
+
+/// Renders the file the user edits: a `Name:` line followed by a free-form,
+/// multi-line description.
+pub fn render_template(fields: &ItemFields) -> String {
+    format!(
+        "{}Name: {}\nDescription:\n{}\n",
+        TEMPLATE_HEADER, fields.name, fields.description
+    )
+}
+
+/// Parses an edited template; an empty file (ignoring the help comments)
+/// aborts. Only the leading comment block is dropped, so descriptions may
+/// contain lines starting with `#`, such as Markdown headings.
+pub fn parse_template(content: &str) -> Result<ItemFields, EditorError> {
+    let lines = content
+        .lines()
+        .skip_while(|line| line.starts_with('#'))
+        .collect::<Vec<_>>();
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return Err(EditorError::Aborted).into_report();
+    }
+
+    let mut name = String::new();
+    let mut description = vec![];
+    let mut in_description = false;
+    for line in lines {
+        if in_description {
+            description.push(line);
+        } else if let Some(value) = line.strip_prefix("Name:") {
+            name = value.trim().to_owned();
+        } else if let Some(value) = line.strip_prefix("Description:") {
+            in_description = true;
+            if !value.trim().is_empty() {
+                description.push(value.trim());
+            }
+        }
+    }
+
+    Ok(ItemFields {
+        name,
+        description: description.join("\n").trim().to_owned(),
+    })
+}
+
+/// Opens `$VISUAL` or `$EDITOR` on a temp file pre-filled with `fields` and
+/// returns what the user saved.
+pub fn edit_fields(fields: &ItemFields) -> Result<ItemFields, EditorError> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| default_editor().to_owned());
+    let mut command = editor.split_whitespace();
+    let program = command.next().ok_or(EditorError::Launch)?;
+
+    let mut file = tempfile::Builder::new()
+        .prefix("bootcamp_jira-")
+        .suffix(".txt")
+        .tempfile()
+        .into_report()
+        .change_context(EditorError::Launch)?;
+    file.write_all(render_template(fields).as_bytes())
+        .into_report()
+        .change_context(EditorError::Launch)?;
+    // Close our handle so editors that replace the file can; the path is
+    // still removed when dropped.
+    let path = file.into_temp_path();
+
+    let status = Command::new(program)
+        .args(command)
+        .arg(&path)
+        .status()
+        .into_report()
+        .change_context(EditorError::Launch)
+        .attach_printable_lazy(|| editor.clone());
+    let content = std::fs::read_to_string(&path)
+        .into_report()
+        .change_context(EditorError::Read);
+
+    if !status?.success() {
+        return Err(EditorError::Aborted).into_report();
+    }
+    parse_template(&content?)
+}
+
+fn default_editor() -> &'static str {
+    if cfg!(windows) {
+        "notepad"
+    } else {
+        "vi"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_template_should_round_trip() {
+        let fields = ItemFields {
+            name: "Login page".to_owned(),
+            description: "Acceptance criteria:\n- [ ] form\n- [ ] errors".to_owned(),
+        };
+
+        let parsed = parse_template(&render_template(&fields)).unwrap();
+        assert_eq!(parsed, fields);
+    }
+
+    #[test]
+    fn parse_template_should_abort_on_empty_file() {
+        assert!(parse_template("").is_err());
+        assert!(parse_template(TEMPLATE_HEADER).is_err());
+        assert!(parse_template("\n  \n").is_err());
+    }
+
+    #[test]
+    fn parse_template_should_keep_hash_lines_in_description() {
+        let fields = ItemFields {
+            name: "Release notes".to_owned(),
+            description: "# Summary\nShipped.\n## Details\n#42 fixed".to_owned(),
+        };
+
+        let parsed = parse_template(&render_template(&fields)).unwrap();
+        assert_eq!(parsed, fields);
+    }
+}
@@ -1,3 +1,4 @@
+mod editor;
 mod pages;
 mod prompts;
 
@@ -1,12 +1,17 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
 use std::rc::Rc;
 
+use chrono::Local;
 use error_stack::{Result, ResultExt};
 
-use crate::db::JiraDatabase;
-use crate::models::Action;
+use crate::db::{JiraDatabase, Sections};
+use crate::models::{
+    format_date, format_timestamp, join_labels, next_label, Action, SortKey, Workflow,
+};
 
 mod page_helpers;
-use page_helpers::*;
+pub use page_helpers::{get_column_string, get_progress_bar, highlight_overdue};
 
 #[derive(Debug)]
 pub enum PageError {
@@ -33,21 +38,38 @@ pub trait Page {
 
 pub struct HomePage {
     pub db: Rc<JiraDatabase>,
+    pub sort: Cell<SortKey>,
+    /// Only epics with this label are listed.
+    pub label: RefCell<Option<String>>,
 }
 impl Page for HomePage {
     fn draw_page(&self) -> Result<(), PageError> {
         println!("----------------------------- EPICS -----------------------------");
-        println!("     id     |               name               |      status      ");
+        println!(
+            "     id     |               name               |      status      |   priority   |    labels    |    due     "
+        );
 
         let db_state = self.db.read_db().change_context(PageError::DrawError)?;
-        let keys = itertools::sorted(db_state.epics.keys());
-        for id in keys {
-            if let Some(epic) = db_state.epics.get(id) {
-                println!(
-                    "{}|{}|{}",
+        let today = Local::now().date_naive();
+        let label = self.label.borrow();
+        for id in db_state.sorted_epic_ids(self.sort.get()) {
+            if let Some(epic) = db_state
+                .epics
+                .get(&id)
+                .filter(|epic| has_label(&epic.labels, label.as_deref()))
+            {
+                let row = format!(
+                    "{}|{}|{}|{}|{}|{}",
                     get_column_string(&id.to_string(), 12),
                     get_column_string(&epic.name, 34),
                     get_column_string(&epic.status.to_string(), 18),
+                    get_column_string(&epic.priority.to_string(), 14),
+                    get_column_string(&join_labels(&epic.labels), 14),
+                    get_column_string(&format_date(epic.due_date), 12),
+                );
+                println!(
+                    "{}",
+                    highlight_overdue(row, epic.is_overdue(&self.db.workflows.epic, today))
                 );
             }
         }
@@ -55,7 +77,11 @@ impl Page for HomePage {
         println!();
         println!();
 
-        println!("[q] quit | [c] create epic | [:id:] navigate to epic");
+        println!(
+            "[q] quit | [c] create epic | [a] assigned to me | [t] trash | [s] sort by {} | [f] label: {} | [z] undo | [y] redo | [:id:] navigate to epic",
+            self.sort.get().next(),
+            label.as_deref().unwrap_or("all")
+        );
 
         Ok(())
     }
@@ -74,6 +100,23 @@ impl Page for HomePage {
             match input {
                 "q" => Ok(Some(Action::Exit)),
                 "c" => Ok(Some(Action::CreateEpic)),
+                "a" => Ok(Some(Action::NavigateToAssignedToMe)),
+                "t" => Ok(Some(Action::NavigateToTrash)),
+                "z" => Ok(Some(Action::Undo)),
+                "y" => Ok(Some(Action::Redo)),
+                "s" => {
+                    self.sort.set(self.sort.get().next());
+                    Ok(None)
+                }
+                "f" => {
+                    let db_state = self.db.read_db().change_context(PageError::DrawError)?;
+                    let mut label = self.label.borrow_mut();
+                    *label = next_label(
+                        db_state.epics.values().map(|epic| &epic.labels),
+                        label.as_deref(),
+                    );
+                    Ok(None)
+                }
                 _ => Ok(None),
             }
         }
@@ -84,9 +127,16 @@ impl Page for HomePage {
     }
 }
 
+fn has_label(labels: &BTreeSet<String>, label: Option<&str>) -> bool {
+    label.is_none_or(|label| labels.contains(label))
+}
+
 pub struct EpicDetail {
     pub epic_id: u32,
     pub db: Rc<JiraDatabase>,
+    pub sort: Cell<SortKey>,
+    /// Only stories with this label are listed.
+    pub label: RefCell<Option<String>>,
 }
 
 impl Page for EpicDetail {
@@ -98,28 +148,72 @@ impl Page for EpicDetail {
             .ok_or(PageError::DrawError)?;
 
         println!("------------------------------ EPIC ------------------------------");
-        println!("  id  |     name     |         description         |    status    ");
-
         println!(
-            "{}|{}|{}|{}",
+            "  id  |     name     |         description         |    status    |   priority   "
+        );
+
+        let today = Local::now().date_naive();
+        let row = format!(
+            "{}|{}|{}|{}|{}",
             get_column_string(&self.epic_id.to_string(), 6),
             get_column_string(&epic.name, 14),
             get_column_string(&epic.description, 29),
-            get_column_string(&epic.status.to_string(), 14)
+            get_column_string(&epic.status.to_string(), 14),
+            get_column_string(&epic.priority.to_string(), 14)
+        );
+        println!(
+            "{}",
+            highlight_overdue(row, epic.is_overdue(&self.db.workflows.epic, today))
+        );
+        println!(
+            "assignee: {} | reporter: {}",
+            db_state.username(epic.assignee),
+            db_state.username(epic.reporter)
+        );
+        println!("labels: {}", join_labels(&epic.labels));
+        println!(
+            "created: {} | updated: {} | due: {}",
+            format_timestamp(epic.created_at),
+            format_timestamp(epic.updated_at),
+            format_date(epic.due_date)
+        );
+        let progress = db_state.progress(epic, &self.db.workflows.story);
+        println!(
+            "points: {} total | {} completed | {} remaining {}",
+            progress.total,
+            progress.completed,
+            progress.remaining(),
+            get_progress_bar(progress.completed, progress.total, 20)
         );
 
         println!();
 
         println!("---------------------------- STORIES ----------------------------");
-        println!("     id     |               name               |      status      ");
+        println!(
+            "     id     |               name               |      status      |   priority   | points |   assignee   |    labels    |    due     "
+        );
 
-        for id in itertools::sorted(epic.stories.iter()) {
-            if let Some(story) = db_state.stories.get(id) {
-                println!(
-                    "{}|{}|{}",
+        let label = self.label.borrow();
+        for id in db_state.sorted_story_ids(&epic.stories, self.sort.get()) {
+            if let Some(story) = db_state
+                .stories
+                .get(&id)
+                .filter(|story| has_label(&story.labels, label.as_deref()))
+            {
+                let row = format!(
+                    "{}|{}|{}|{}|{}|{}|{}|{}",
                     get_column_string(&id.to_string(), 12),
                     get_column_string(&story.name, 34),
-                    get_column_string(&story.status.to_string(), 17)
+                    get_column_string(&story.status.to_string(), 18),
+                    get_column_string(&story.priority.to_string(), 14),
+                    get_column_string(&story.points.map_or(String::new(), |p| p.to_string()), 8),
+                    get_column_string(db_state.username(story.assignee), 14),
+                    get_column_string(&join_labels(&story.labels), 14),
+                    get_column_string(&format_date(story.due_date), 12)
+                );
+                println!(
+                    "{}",
+                    highlight_overdue(row, story.is_overdue(&self.db.workflows.story, today))
                 );
             }
         }
@@ -127,7 +221,11 @@ impl Page for EpicDetail {
         println!();
         println!();
 
-        println!("[p] previous | [u] update epic | [d] delete epic | [c] create story | [:id:] navigate to story");
+        println!(
+            "[p] previous | [u] update epic | [e] edit epic | [d] delete epic | [a] assign epic | [l] labels | [h] history | [c] create story | [s] sort by {} | [f] label: {} | [z] undo | [y] redo | [:id:] navigate to story",
+            self.sort.get().next(),
+            label.as_deref().unwrap_or("all")
+        );
 
         Ok(())
     }
@@ -151,9 +249,39 @@ impl Page for EpicDetail {
                 "u" => Ok(Some(Action::UpdateEpicStatus {
                     epic_id: self.epic_id,
                 })),
+                "e" => Ok(Some(Action::EditEpic {
+                    epic_id: self.epic_id,
+                })),
                 "d" => Ok(Some(Action::DeleteEpic {
                     epic_id: self.epic_id,
                 })),
+                "a" => Ok(Some(Action::AssignEpic {
+                    epic_id: self.epic_id,
+                })),
+                "l" => Ok(Some(Action::EditEpicLabels {
+                    epic_id: self.epic_id,
+                })),
+                "h" => Ok(Some(Action::NavigateToHistory {
+                    item_id: self.epic_id,
+                })),
+                "z" => Ok(Some(Action::Undo)),
+                "y" => Ok(Some(Action::Redo)),
+                "s" => {
+                    self.sort.set(self.sort.get().next());
+                    Ok(None)
+                }
+                "f" => {
+                    let db_state = self.db.read_db().change_context(PageError::DrawError)?;
+                    let stories = db_state
+                        .epics
+                        .get(&self.epic_id)
+                        .map_or(&[][..], |epic| &epic.stories)
+                        .iter()
+                        .filter_map(|id| db_state.stories.get(id));
+                    let mut label = self.label.borrow_mut();
+                    *label = next_label(stories.map(|story| &story.labels), label.as_deref());
+                    Ok(None)
+                }
                 "c" => Ok(Some(Action::CreateStory {
                     epic_id: self.epic_id,
                 })),
@@ -182,20 +310,104 @@ impl Page for StoryDetail {
             .ok_or(PageError::DrawError)?;
 
         println!("------------------------------ STORY ------------------------------");
-        println!("  id  |     name     |         description         |    status    ");
+        println!(
+            "  id  |     name     |         description         |    status    |   priority   "
+        );
 
         println!(
-            "{}|{}|{}|{}",
+            "{}|{}|{}|{}|{}",
             get_column_string(&self.story_id.to_string(), 6),
             get_column_string(&story.name, 14),
             get_column_string(&story.description, 29),
-            get_column_string(&story.status.to_string(), 15)
+            get_column_string(&story.status.to_string(), 14),
+            get_column_string(&story.priority.to_string(), 14)
+        );
+        println!(
+            "assignee: {} | reporter: {}",
+            db_state.username(story.assignee),
+            db_state.username(story.reporter)
         );
+        println!("labels: {}", join_labels(&story.labels));
+        println!(
+            "created: {} | updated: {} | due: {}",
+            format_timestamp(story.created_at),
+            format_timestamp(story.updated_at),
+            format_date(story.due_date)
+        );
+        if let Some(points) = story.points {
+            println!("points: {}", points);
+        }
+
+        println!();
+
+        // Sub-tasks follow the default workflow.
+        let subtasks = Workflow::default();
+        let checklist = db_state.checklist(story);
+        let done = checklist
+            .iter()
+            .filter(|(_, subtask)| subtasks.is_done(&subtask.status))
+            .count();
+        println!("--------------------------- SUB-TASKS ---------------------------");
+        println!("{} of {} done", done, checklist.len());
+        for (id, subtask) in checklist {
+            println!(
+                "[{}] {}|{}|{}",
+                if subtasks.is_done(&subtask.status) {
+                    "x"
+                } else {
+                    " "
+                },
+                get_column_string(&id.to_string(), 8),
+                get_column_string(&subtask.name, 34),
+                get_column_string(&subtask.status.to_string(), 18),
+            );
+        }
+
+        println!();
+
+        println!("----------------------------- LINKS -----------------------------");
+        for (id, link) in db_state.links_of(self.story_id) {
+            let other_id = if link.from == self.story_id {
+                link.to
+            } else {
+                link.from
+            };
+            let other = &db_state.stories[&other_id];
+            println!(
+                "{}|{}|{}|{}",
+                get_column_string(&id.to_string(), 8),
+                get_column_string(&link.describe(self.story_id), 24),
+                get_column_string(&other.name, 34),
+                get_column_string(&other.status.to_string(), 18),
+            );
+        }
+
+        println!();
+
+        println!("---------------------------- COMMENTS ----------------------------");
+        for (id, comment) in db_state.comments_on(self.story_id) {
+            println!(
+                "#{} {} at {}{}",
+                id,
+                db_state.username(comment.author),
+                format_timestamp(Some(comment.created_at)),
+                if comment.edited_at.is_some() {
+                    " (edited)"
+                } else {
+                    ""
+                }
+            );
+            for line in comment.body.lines() {
+                println!("    {}", line);
+            }
+        }
 
         println!();
         println!();
 
-        println!("[p] previous | [u] update story | [d] delete story");
+        println!(
+            "[p] previous | [u] update story | [e] edit story | [d] delete story | [m] move story | [a] assign story | [l] labels | [n] new comment | [c] create sub-task | [k] link story | [x] remove link | [h] history | [z] undo | [y] redo | [:id:] navigate to sub-task"
+        );
 
         Ok(())
     }
@@ -203,8 +415,16 @@ impl Page for StoryDetail {
     fn handle_input(&self, input: &str) -> Result<Option<Action>, PageError> {
         if input.is_empty() {
             Ok(None)
-        } else if let Ok(_id) = input.parse::<u32>() {
-            Ok(None)
+        } else if let Ok(id) = input.parse::<u32>() {
+            let db_state = self.db.read_db().change_context(PageError::DrawError)?;
+            let is_subtask = db_state
+                .stories
+                .get(&self.story_id)
+                .is_some_and(|story| story.subtasks.contains(&id));
+            Ok(is_subtask.then_some(Action::NavigateToSubTaskDetail {
+                story_id: self.story_id,
+                subtask_id: id,
+            }))
         } else {
             match input {
                 "p" => Ok(Some(Action::NavigateToPreviousPage)),
@@ -212,10 +432,40 @@ impl Page for StoryDetail {
                 "u" => Ok(Some(Action::UpdateStoryStatus {
                     story_id: self.story_id,
                 })),
+                "e" => Ok(Some(Action::EditStory {
+                    story_id: self.story_id,
+                })),
                 "d" => Ok(Some(Action::DeleteStory {
                     epic_id: self.epic_id,
                     story_id: self.story_id,
                 })),
+                "m" => Ok(Some(Action::MoveStory {
+                    epic_id: self.epic_id,
+                    story_id: self.story_id,
+                })),
+                "a" => Ok(Some(Action::AssignStory {
+                    story_id: self.story_id,
+                })),
+                "l" => Ok(Some(Action::EditStoryLabels {
+                    story_id: self.story_id,
+                })),
+                "n" => Ok(Some(Action::AddComment {
+                    item_id: self.story_id,
+                })),
+                "c" => Ok(Some(Action::CreateSubTask {
+                    story_id: self.story_id,
+                })),
+                "k" => Ok(Some(Action::AddStoryLink {
+                    story_id: self.story_id,
+                })),
+                "x" => Ok(Some(Action::RemoveStoryLink {
+                    story_id: self.story_id,
+                })),
+                "h" => Ok(Some(Action::NavigateToHistory {
+                    item_id: self.story_id,
+                })),
+                "z" => Ok(Some(Action::Undo)),
+                "y" => Ok(Some(Action::Redo)),
                 _ => Ok(None),
             }
         }
@@ -226,46 +476,308 @@ impl Page for StoryDetail {
     }
 }
 
+pub struct SubTaskDetail {
+    pub story_id: u32,
+    pub subtask_id: u32,
+    pub db: Rc<JiraDatabase>,
+}
+
+impl Page for SubTaskDetail {
+    fn draw_page(&self) -> Result<(), PageError> {
+        let db_state = self.db.read_db().change_context(PageError::DrawError)?;
+        let subtask = db_state
+            .subtasks
+            .get(&self.subtask_id)
+            .ok_or(PageError::DrawError)?;
+        let story = db_state
+            .stories
+            .get(&self.story_id)
+            .ok_or(PageError::DrawError)?;
+
+        println!("---------------------------- SUB-TASK ----------------------------");
+        println!("  id  |     name     |         description         |    status    ");
+        println!(
+            "{}|{}|{}|{}",
+            get_column_string(&self.subtask_id.to_string(), 6),
+            get_column_string(&subtask.name, 14),
+            get_column_string(&subtask.description, 29),
+            get_column_string(&subtask.status.to_string(), 14)
+        );
+        println!("story: {} {}", self.story_id, story.name);
+        println!(
+            "created: {} | updated: {}",
+            format_timestamp(Some(subtask.created_at)),
+            format_timestamp(Some(subtask.updated_at))
+        );
+
+        println!();
+        println!();
+
+        println!(
+            "[p] previous | [u] update sub-task | [d] delete sub-task | [h] history | [z] undo | [y] redo"
+        );
+
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>, PageError> {
+        match input {
+            "p" => Ok(Some(Action::NavigateToPreviousPage)),
+            "q" => Ok(Some(Action::Exit)),
+            "u" => Ok(Some(Action::UpdateSubTaskStatus {
+                subtask_id: self.subtask_id,
+            })),
+            "d" => Ok(Some(Action::DeleteSubTask {
+                story_id: self.story_id,
+                subtask_id: self.subtask_id,
+            })),
+            "h" => Ok(Some(Action::NavigateToHistory {
+                item_id: self.subtask_id,
+            })),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            _ => Ok(None),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Epics and stories assigned to the current user.
+pub struct AssignedToMe {
+    pub username: String,
+    pub db: Rc<JiraDatabase>,
+}
+
+impl Page for AssignedToMe {
+    fn draw_page(&self) -> Result<(), PageError> {
+        let db_state = self.db.read_db().change_context(PageError::DrawError)?;
+        let user_id = db_state.find_user(&self.username);
+        let is_mine = |assignee: Option<u32>| user_id.is_some() && assignee == user_id;
+
+        println!("------------------------ ASSIGNED TO ME ------------------------");
+        println!("user: {}", self.username);
+        println!();
+
+        println!("----------------------------- EPICS -----------------------------");
+        println!("     id     |               name               |      status      ");
+        for id in itertools::sorted(db_state.epics.keys()) {
+            let epic = &db_state.epics[id];
+            if is_mine(epic.assignee) {
+                println!(
+                    "{}|{}|{}",
+                    get_column_string(&id.to_string(), 12),
+                    get_column_string(&epic.name, 34),
+                    get_column_string(&epic.status.to_string(), 18),
+                );
+            }
+        }
+
+        println!();
+
+        println!("---------------------------- STORIES ----------------------------");
+        println!("     id     |               name               |      status      ");
+        for id in itertools::sorted(db_state.stories.keys()) {
+            let story = &db_state.stories[id];
+            if is_mine(story.assignee) {
+                println!(
+                    "{}|{}|{}",
+                    get_column_string(&id.to_string(), 12),
+                    get_column_string(&story.name, 34),
+                    get_column_string(&story.status.to_string(), 18),
+                );
+            }
+        }
+
+        println!();
+        println!();
+
+        println!("[p] previous | [z] undo | [y] redo | [:id:] navigate to epic or story");
+
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>, PageError> {
+        if input.is_empty() {
+            Ok(None)
+        } else if let Ok(id) = input.parse::<u32>() {
+            let db_state = self.db.read_db().change_context(PageError::DrawError)?;
+            if db_state.epics.contains_key(&id) {
+                return Ok(Some(Action::NavigateToEpicDetail { epic_id: id }));
+            }
+            let epic_id = db_state
+                .epics
+                .iter()
+                .find(|(_, epic)| epic.stories.contains(&id))
+                .map(|(epic_id, _)| *epic_id);
+            Ok(epic_id.map(|epic_id| Action::NavigateToStoryDetail {
+                epic_id,
+                story_id: id,
+            }))
+        } else {
+            match input {
+                "p" => Ok(Some(Action::NavigateToPreviousPage)),
+                "q" => Ok(Some(Action::Exit)),
+                "z" => Ok(Some(Action::Undo)),
+                "y" => Ok(Some(Action::Redo)),
+                _ => Ok(None),
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Audit events of one epic, story or sub-task, newest first.
+pub struct History {
+    pub item_id: u32,
+    pub db: Rc<JiraDatabase>,
+}
+
+impl Page for History {
+    fn draw_page(&self) -> Result<(), PageError> {
+        let db_state = self
+            .db
+            .read_db_with(Sections::HISTORY)
+            .change_context(PageError::DrawError)?;
+
+        println!("---------------------------- HISTORY ----------------------------");
+        println!("item: {}", self.item_id);
+        println!(
+            "       when       |    who     |    field     |        old         |        new         "
+        );
+        for event in db_state.history_of(self.item_id).into_iter().rev() {
+            println!(
+                "{}|{}|{}|{}|{}",
+                get_column_string(&format_timestamp(Some(event.at)), 18),
+                get_column_string(event.actor.as_deref().unwrap_or(""), 12),
+                get_column_string(&event.field, 14),
+                get_column_string(&event.old, 20),
+                get_column_string(&event.new, 20),
+            );
+        }
+
+        println!();
+        println!();
+
+        println!("[p] previous | [z] undo | [y] redo");
+
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>, PageError> {
+        match input {
+            "p" => Ok(Some(Action::NavigateToPreviousPage)),
+            "q" => Ok(Some(Action::Exit)),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            _ => Ok(None),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// Deleted epics and stories, most recently deleted first.
+pub struct Trash {
+    pub db: Rc<JiraDatabase>,
+}
+
+impl Page for Trash {
+    fn draw_page(&self) -> Result<(), PageError> {
+        let db_state = self
+            .db
+            .read_db_with(Sections::TRASH)
+            .change_context(PageError::DrawError)?;
+
+        println!("----------------------------- TRASH -----------------------------");
+        println!("     id     |  type  |               name               |     deleted      ");
+        for (id, entry) in db_state.trashed() {
+            println!(
+                "{}|{}|{}|{}",
+                get_column_string(&id.to_string(), 12),
+                get_column_string(entry.kind(), 8),
+                get_column_string(entry.name(), 34),
+                get_column_string(&format_timestamp(Some(entry.deleted_at)), 18),
+            );
+        }
+
+        println!();
+        println!();
+
+        println!("[p] previous | [r] restore | [z] undo | [y] redo");
+
+        Ok(())
+    }
+
+    fn handle_input(&self, input: &str) -> Result<Option<Action>, PageError> {
+        match input {
+            "p" => Ok(Some(Action::NavigateToPreviousPage)),
+            "q" => Ok(Some(Action::Exit)),
+            "r" => Ok(Some(Action::RestoreFromTrash)),
+            "z" => Ok(Some(Action::Undo)),
+            "y" => Ok(Some(Action::Redo)),
+            _ => Ok(None),
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db::test_utils::MockDB;
-    use crate::models::{Epic, Story};
+    use crate::models::{Epic, Story, SubTask};
 
     mod home_page {
         use super::*;
 
         #[test]
         fn draw_page_should_not_throw_error() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
-            let page = HomePage { db };
+            let page = HomePage {
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
             assert_eq!(page.draw_page().is_ok(), true);
         }
 
         #[test]
         fn handle_input_should_not_throw_error() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
-            let page = HomePage { db };
+            let page = HomePage {
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
             assert_eq!(page.handle_input("").is_ok(), true);
         }
 
         #[test]
         fn handle_input_should_return_the_correct_actions() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
             let epic = Epic::new("".to_owned(), "".to_owned());
 
             let epic_id = db.create_epic(epic).unwrap();
 
-            let page = HomePage { db };
+            let page = HomePage {
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
 
             let q = "q";
             let c = "c";
@@ -277,6 +789,18 @@ mod tests {
 
             assert_eq!(page.handle_input(q).unwrap(), Some(Action::Exit));
             assert_eq!(page.handle_input(c).unwrap(), Some(Action::CreateEpic));
+            assert_eq!(
+                page.handle_input("a").unwrap(),
+                Some(Action::NavigateToAssignedToMe)
+            );
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
+            assert_eq!(
+                page.handle_input("t").unwrap(),
+                Some(Action::NavigateToTrash)
+            );
+            assert_eq!(page.handle_input("s").unwrap(), None);
+            assert_eq!(page.sort.get(), SortKey::Priority);
             assert_eq!(
                 page.handle_input(&valid_epic_id).unwrap(),
                 Some(Action::NavigateToEpicDetail { epic_id: 1 })
@@ -294,50 +818,112 @@ mod tests {
         }
     }
 
+    mod label_filter {
+        use super::*;
+        use crate::models::LabelUpdate;
+
+        #[test]
+        fn handle_input_should_cycle_through_labels() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+            db.update_epic_labels(epic_id, LabelUpdate::parse("ui backend"))
+                .unwrap();
+
+            let page = HomePage {
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
+
+            assert_eq!(page.handle_input("f").unwrap(), None);
+            assert_eq!(page.label.borrow().as_deref(), Some("backend"));
+            page.handle_input("f").unwrap();
+            assert_eq!(page.label.borrow().as_deref(), Some("ui"));
+            page.handle_input("f").unwrap();
+            assert_eq!(page.label.borrow().as_deref(), None);
+            assert!(page.draw_page().is_ok());
+        }
+
+        #[test]
+        fn handle_input_should_filter_stories_of_epic() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+            let story_id = db
+                .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+                .unwrap();
+            db.update_story_labels(story_id, LabelUpdate::parse("backend"))
+                .unwrap();
+
+            let page = EpicDetail {
+                epic_id,
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
+
+            page.handle_input("f").unwrap();
+            assert_eq!(page.label.borrow().as_deref(), Some("backend"));
+            assert!(page.draw_page().is_ok());
+            page.handle_input("f").unwrap();
+            assert_eq!(page.label.borrow().as_deref(), None);
+        }
+    }
+
     mod epic_detail_page {
         use super::*;
 
         #[test]
         fn draw_page_should_not_throw_error() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
                 .unwrap();
 
-            let page = EpicDetail { epic_id, db };
+            let page = EpicDetail {
+                epic_id,
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
             assert_eq!(page.draw_page().is_ok(), true);
         }
 
         #[test]
         fn handle_input_should_not_throw_error() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
                 .unwrap();
 
-            let page = EpicDetail { epic_id, db };
+            let page = EpicDetail {
+                epic_id,
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
             assert_eq!(page.handle_input("").is_ok(), true);
         }
 
         #[test]
         fn draw_page_should_throw_error_for_invalid_epic_id() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
-            let page = EpicDetail { epic_id: 999, db };
+            let page = EpicDetail {
+                epic_id: 999,
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
             assert_eq!(page.draw_page().is_err(), true);
         }
 
         #[test]
         fn handle_input_should_return_the_correct_actions() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
@@ -346,10 +932,16 @@ mod tests {
                 .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
                 .unwrap();
 
-            let page = EpicDetail { epic_id, db };
+            let page = EpicDetail {
+                epic_id,
+                db,
+                sort: Cell::default(),
+                label: RefCell::default(),
+            };
 
             let p = "p";
             let u = "u";
+            let e = "e";
             let d = "d";
             let c = "c";
             let invalid_story_id = "999";
@@ -365,6 +957,10 @@ mod tests {
                 page.handle_input(u).unwrap(),
                 Some(Action::UpdateEpicStatus { epic_id: 1 })
             );
+            assert_eq!(
+                page.handle_input(e).unwrap(),
+                Some(Action::EditEpic { epic_id: 1 })
+            );
             assert_eq!(
                 page.handle_input(d).unwrap(),
                 Some(Action::DeleteEpic { epic_id: 1 })
@@ -373,6 +969,16 @@ mod tests {
                 page.handle_input(c).unwrap(),
                 Some(Action::CreateStory { epic_id: 1 })
             );
+            assert_eq!(
+                page.handle_input("l").unwrap(),
+                Some(Action::EditEpicLabels { epic_id: 1 })
+            );
+            assert_eq!(
+                page.handle_input("h").unwrap(),
+                Some(Action::NavigateToHistory { item_id: 1 })
+            );
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
             assert_eq!(
                 page.handle_input(&story_id.to_string()).unwrap(),
                 Some(Action::NavigateToStoryDetail {
@@ -398,9 +1004,7 @@ mod tests {
 
         #[test]
         fn draw_page_should_not_throw_error() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
@@ -419,9 +1023,7 @@ mod tests {
 
         #[test]
         fn handle_input_should_not_throw_error() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
@@ -440,9 +1042,7 @@ mod tests {
 
         #[test]
         fn draw_page_should_throw_error_for_invalid_story_id() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
@@ -461,9 +1061,7 @@ mod tests {
 
         #[test]
         fn handle_input_should_return_the_correct_actions() {
-            let db = Rc::new(JiraDatabase {
-                database: Box::new(MockDB::new()),
-            });
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
 
             let epic_id = db
                 .create_epic(Epic::new("".to_owned(), "".to_owned()))
@@ -480,7 +1078,9 @@ mod tests {
 
             let p = "p";
             let u = "u";
+            let e = "e";
             let d = "d";
+            let m = "m";
             let some_number = "1";
             let junk_input = "j983f2j";
             let junk_input_with_valid_prefix = "p983f2j";
@@ -490,10 +1090,44 @@ mod tests {
                 page.handle_input(p).unwrap(),
                 Some(Action::NavigateToPreviousPage)
             );
+            assert_eq!(
+                page.handle_input(m).unwrap(),
+                Some(Action::MoveStory { epic_id, story_id })
+            );
+            assert_eq!(
+                page.handle_input("l").unwrap(),
+                Some(Action::EditStoryLabels { story_id })
+            );
+            assert_eq!(
+                page.handle_input("n").unwrap(),
+                Some(Action::AddComment { item_id: story_id })
+            );
+            assert_eq!(
+                page.handle_input("c").unwrap(),
+                Some(Action::CreateSubTask { story_id })
+            );
+            assert_eq!(
+                page.handle_input("k").unwrap(),
+                Some(Action::AddStoryLink { story_id })
+            );
+            assert_eq!(
+                page.handle_input("x").unwrap(),
+                Some(Action::RemoveStoryLink { story_id })
+            );
+            assert_eq!(
+                page.handle_input("h").unwrap(),
+                Some(Action::NavigateToHistory { item_id: story_id })
+            );
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
             assert_eq!(
                 page.handle_input(u).unwrap(),
                 Some(Action::UpdateStoryStatus { story_id })
             );
+            assert_eq!(
+                page.handle_input(e).unwrap(),
+                Some(Action::EditStory { story_id })
+            );
             assert_eq!(
                 page.handle_input(d).unwrap(),
                 Some(Action::DeleteStory { epic_id, story_id })
@@ -510,4 +1144,218 @@ mod tests {
             );
         }
     }
+
+    mod subtask_detail_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_throw_error() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+            let story_id = db
+                .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+                .unwrap();
+            let subtask_id = db
+                .create_subtask(SubTask::new("".to_owned(), "".to_owned()), story_id)
+                .unwrap();
+
+            let story_page = StoryDetail {
+                epic_id,
+                story_id,
+                db: Rc::clone(&db),
+            };
+            assert!(story_page.draw_page().is_ok());
+
+            let page = SubTaskDetail {
+                story_id,
+                subtask_id,
+                db: Rc::clone(&db),
+            };
+            assert!(page.draw_page().is_ok());
+
+            let page = SubTaskDetail {
+                story_id,
+                subtask_id: 999,
+                db,
+            };
+            assert!(page.draw_page().is_err());
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+            let story_id = db
+                .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+                .unwrap();
+            let subtask_id = db
+                .create_subtask(SubTask::new("".to_owned(), "".to_owned()), story_id)
+                .unwrap();
+
+            let story_page = StoryDetail {
+                epic_id,
+                story_id,
+                db: Rc::clone(&db),
+            };
+            assert_eq!(
+                story_page.handle_input(&subtask_id.to_string()).unwrap(),
+                Some(Action::NavigateToSubTaskDetail {
+                    story_id,
+                    subtask_id
+                })
+            );
+            assert_eq!(
+                story_page.handle_input(&story_id.to_string()).unwrap(),
+                None
+            );
+
+            let page = SubTaskDetail {
+                story_id,
+                subtask_id,
+                db,
+            };
+            assert_eq!(
+                page.handle_input("p").unwrap(),
+                Some(Action::NavigateToPreviousPage)
+            );
+            assert_eq!(
+                page.handle_input("u").unwrap(),
+                Some(Action::UpdateSubTaskStatus { subtask_id })
+            );
+            assert_eq!(
+                page.handle_input("d").unwrap(),
+                Some(Action::DeleteSubTask {
+                    story_id,
+                    subtask_id
+                })
+            );
+            assert_eq!(
+                page.handle_input("h").unwrap(),
+                Some(Action::NavigateToHistory {
+                    item_id: subtask_id
+                })
+            );
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("1").unwrap(), None);
+        }
+    }
+
+    mod assigned_to_me_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_throw_error() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+
+            let page = AssignedToMe {
+                username: "jdoe".to_owned(),
+                db,
+            };
+            assert!(page.draw_page().is_ok());
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+            let story_id = db
+                .create_story(Story::new("".to_owned(), "".to_owned()), epic_id)
+                .unwrap();
+
+            let page = AssignedToMe {
+                username: "jdoe".to_owned(),
+                db,
+            };
+
+            assert_eq!(
+                page.handle_input("p").unwrap(),
+                Some(Action::NavigateToPreviousPage)
+            );
+            assert_eq!(
+                page.handle_input(&epic_id.to_string()).unwrap(),
+                Some(Action::NavigateToEpicDetail { epic_id })
+            );
+            assert_eq!(
+                page.handle_input(&story_id.to_string()).unwrap(),
+                Some(Action::NavigateToStoryDetail { epic_id, story_id })
+            );
+            assert_eq!(page.handle_input("999").unwrap(), None);
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
+        }
+    }
+
+    mod history_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_throw_error() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+
+            let page = History {
+                item_id: epic_id,
+                db,
+            };
+            assert!(page.draw_page().is_ok());
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+
+            let page = History { item_id: 1, db };
+
+            assert_eq!(
+                page.handle_input("p").unwrap(),
+                Some(Action::NavigateToPreviousPage)
+            );
+            assert_eq!(page.handle_input("1").unwrap(), None);
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("y").unwrap(), Some(Action::Redo));
+        }
+    }
+
+    mod trash_page {
+        use super::*;
+
+        #[test]
+        fn draw_page_should_not_throw_error() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+            let epic_id = db
+                .create_epic(Epic::new("".to_owned(), "".to_owned()))
+                .unwrap();
+            db.delete_epic(epic_id).unwrap();
+
+            let page = Trash { db };
+            assert!(page.draw_page().is_ok());
+        }
+
+        #[test]
+        fn handle_input_should_return_the_correct_actions() {
+            let db = Rc::new(JiraDatabase::with_database(Box::new(MockDB::new())));
+
+            let page = Trash { db };
+
+            assert_eq!(
+                page.handle_input("p").unwrap(),
+                Some(Action::NavigateToPreviousPage)
+            );
+            assert_eq!(
+                page.handle_input("r").unwrap(),
+                Some(Action::RestoreFromTrash)
+            );
+            assert_eq!(page.handle_input("z").unwrap(), Some(Action::Undo));
+            assert_eq!(page.handle_input("1").unwrap(), None);
+        }
+    }
 }
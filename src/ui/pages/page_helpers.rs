@@ -1,3 +1,6 @@
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
 use ellipse::Ellipse;
 
 pub fn get_column_string(text: &str, width: usize) -> String {
@@ -16,10 +19,60 @@ pub fn get_column_string(text: &str, width: usize) -> String {
     }
 }
 
+/// A `[###---] 50%` bar, `width` characters between the brackets.
+pub fn get_progress_bar(done: u32, total: u32, width: usize) -> String {
+    let ratio = if total == 0 {
+        0.0
+    } else {
+        done as f64 / total as f64
+    };
+    let filled = (ratio * width as f64).round() as usize;
+    format!(
+        "[{}{}] {}%",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        (ratio * 100.0).round()
+    )
+}
+
+/// Shows `row` in red when `overdue`, if stdout is a terminal and `NO_COLOR`
+/// is not set.
+pub fn highlight_overdue(row: String, overdue: bool) -> String {
+    static USE_COLOR: OnceLock<bool> = OnceLock::new();
+    let use_color = *USE_COLOR.get_or_init(|| {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        !no_color && std::io::stdout().is_terminal()
+    });
+    paint_overdue(row, overdue && use_color)
+}
+
+fn paint_overdue(row: String, paint: bool) -> String {
+    if paint {
+        format!("\x1b[31m{}\x1b[0m", row)
+    } else {
+        row
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_get_progress_bar() {
+        assert_eq!(get_progress_bar(0, 0, 4), "[----] 0%");
+        assert_eq!(get_progress_bar(1, 2, 4), "[##--] 50%");
+        assert_eq!(get_progress_bar(8, 8, 4), "[####] 100%");
+        assert_eq!(get_progress_bar(1, 3, 10), "[###-------] 33%");
+    }
+
+    #[test]
+    fn test_highlight_overdue() {
+        assert_eq!(highlight_overdue("row".to_owned(), false), "row");
+        assert_eq!(paint_overdue("row".to_owned(), false), "row");
+        assert_eq!(paint_overdue("row".to_owned(), true), "\x1b[31mrow\x1b[0m");
+    }
+
     #[test]
     fn test_get_column_string() {
         let text1 = "";
@@ -1,14 +1,58 @@
 use crate::{
-    io_utils::get_user_input,
-    models::{Epic, Status, Story},
+    io_utils::{get_user_input, wait_for_key_press},
+    models::{
+        join_labels, DBState, Epic, EpicUpdate, LabelUpdate, Link, LinkKind, Priority, Status,
+        Story, StoryUpdate, SubTask,
+    },
 };
 
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+
+use super::editor::{edit_fields, ItemFields};
+
+/// Picks the epic to move a story to, given the epic it is currently in.
+type MoveStoryPrompt = Box<dyn Fn(&DBState, u32) -> Option<u32>>;
+
+/// Picks an assignee from the user directory: `Some(None)` unassigns, `None`
+/// cancels.
+type AssignPrompt = Box<dyn Fn(&DBState) -> Option<Option<u32>>>;
+
+/// Asks which labels to add and remove, given the current ones.
+type LabelsPrompt = Box<dyn Fn(&BTreeSet<String>) -> LabelUpdate>;
+
+/// Picks one of the states the workflow allows next.
+type StatusPrompt = Box<dyn Fn(&[Status]) -> Option<Status>>;
+
+/// Asks for the type and the other story of a new link from the given story.
+type LinkPrompt = Box<dyn Fn(&DBState, u32) -> Option<Link>>;
+
+/// Picks one of the given story's links to remove.
+type UnlinkPrompt = Box<dyn Fn(&DBState, u32) -> Option<u32>>;
+
+/// Picks a trashed epic or story to restore.
+type RestorePrompt = Box<dyn Fn(&DBState) -> Option<u32>>;
+
 pub struct Prompts {
-    pub create_epic: Box<dyn Fn() -> Epic>,
-    pub create_story: Box<dyn Fn() -> Story>,
+    pub create_epic: Box<dyn Fn() -> Option<Epic>>,
+    pub create_story: Box<dyn Fn() -> Option<Story>>,
+    pub create_subtask: Box<dyn Fn() -> Option<SubTask>>,
+    pub edit_epic: Box<dyn Fn(&Epic) -> EpicUpdate>,
+    pub edit_story: Box<dyn Fn(&Story) -> StoryUpdate>,
     pub delete_epic: Box<dyn Fn() -> bool>,
     pub delete_story: Box<dyn Fn() -> bool>,
-    pub update_status: Box<dyn Fn() -> Option<Status>>,
+    pub delete_subtask: Box<dyn Fn() -> bool>,
+    pub move_story: MoveStoryPrompt,
+    pub assign: AssignPrompt,
+    pub edit_labels: LabelsPrompt,
+    pub new_comment: Box<dyn Fn() -> Option<String>>,
+    pub link: LinkPrompt,
+    pub unlink: UnlinkPrompt,
+    pub restore: RestorePrompt,
+    pub update_status: StatusPrompt,
+    pub close_stories: Box<dyn Fn() -> bool>,
+    pub reload_and_retry: Box<dyn Fn() -> bool>,
 }
 
 impl Prompts {
@@ -16,34 +60,227 @@ impl Prompts {
         Self {
             create_epic: Box::new(create_epic_prompt),
             create_story: Box::new(create_story_prompt),
+            create_subtask: Box::new(create_subtask_prompt),
+            edit_epic: Box::new(edit_epic_prompt),
+            edit_story: Box::new(edit_story_prompt),
             delete_epic: Box::new(delete_epic_prompt),
             delete_story: Box::new(delete_story_prompt),
+            delete_subtask: Box::new(delete_subtask_prompt),
+            move_story: Box::new(move_story_prompt),
+            assign: Box::new(assign_prompt),
+            edit_labels: Box::new(edit_labels_prompt),
+            new_comment: Box::new(new_comment_prompt),
+            link: Box::new(link_prompt),
+            unlink: Box::new(unlink_prompt),
+            restore: Box::new(restore_prompt),
             update_status: Box::new(update_status_prompt),
+            close_stories: Box::new(close_stories_prompt),
+            reload_and_retry: Box::new(reload_and_retry_prompt),
+        }
+    }
+
+    /// Same as `new`, but names and descriptions are written in `$VISUAL` or
+    /// `$EDITOR`, which allows multi-line descriptions.
+    pub fn with_editor() -> Self {
+        Self {
+            create_epic: Box::new(create_epic_editor_prompt),
+            create_story: Box::new(create_story_editor_prompt),
+            edit_epic: Box::new(edit_epic_editor_prompt),
+            edit_story: Box::new(edit_story_editor_prompt),
+            ..Self::new()
         }
     }
 }
 
-fn create_epic_prompt() -> Epic {
+fn create_epic_prompt() -> Option<Epic> {
     println!("----------------------------");
     println!("Epic Name:");
     let name = get_user_input();
     println!("Epic Description:");
     let description = get_user_input();
-    Epic::new(name, description)
+    let mut epic = Epic::new(name, description);
+    epic.priority = priority_prompt(epic.priority).unwrap_or_default();
+    epic.due_date = due_date_prompt(None).flatten();
+    Some(epic)
 }
 
-fn create_story_prompt() -> Story {
+fn create_story_prompt() -> Option<Story> {
     println!("----------------------------");
     println!("Story Name:");
     let name = get_user_input();
     println!("Story Description:");
     let description = get_user_input();
-    Story::new(name, description)
+    let mut story = Story::new(name, description);
+    story.priority = priority_prompt(story.priority).unwrap_or_default();
+    story.points = points_prompt(None).flatten();
+    story.due_date = due_date_prompt(None).flatten();
+    Some(story)
+}
+
+fn create_subtask_prompt() -> Option<SubTask> {
+    println!("----------------------------");
+    println!("Sub-task Name:");
+    let name = get_user_input();
+    println!("Sub-task Description:");
+    let description = get_user_input();
+    Some(SubTask::new(name, description))
+}
+
+fn create_epic_editor_prompt() -> Option<Epic> {
+    let fields = editor_prompt(&ItemFields::default())?;
+    let mut epic = Epic::new(fields.name, fields.description);
+    epic.priority = priority_prompt(epic.priority).unwrap_or_default();
+    epic.due_date = due_date_prompt(None).flatten();
+    Some(epic)
+}
+
+fn create_story_editor_prompt() -> Option<Story> {
+    let fields = editor_prompt(&ItemFields::default())?;
+    let mut story = Story::new(fields.name, fields.description);
+    story.priority = priority_prompt(story.priority).unwrap_or_default();
+    story.points = points_prompt(None).flatten();
+    story.due_date = due_date_prompt(None).flatten();
+    Some(story)
+}
+
+fn edit_epic_editor_prompt(epic: &Epic) -> EpicUpdate {
+    let current = ItemFields {
+        name: epic.name.clone(),
+        description: epic.description.clone(),
+    };
+    let (name, description) = changed_fields(&current);
+    EpicUpdate {
+        name,
+        description,
+        priority: priority_prompt(epic.priority),
+        due_date: due_date_prompt(epic.due_date),
+    }
+}
+
+fn edit_story_editor_prompt(story: &Story) -> StoryUpdate {
+    let current = ItemFields {
+        name: story.name.clone(),
+        description: story.description.clone(),
+    };
+    let (name, description) = changed_fields(&current);
+    StoryUpdate {
+        name,
+        description,
+        priority: priority_prompt(story.priority),
+        points: points_prompt(story.points),
+        due_date: due_date_prompt(story.due_date),
+    }
+}
+
+fn changed_fields(current: &ItemFields) -> (Option<String>, Option<String>) {
+    match editor_prompt(current) {
+        Some(edited) => (
+            Some(edited.name).filter(|name| name != &current.name),
+            Some(edited.description).filter(|description| description != &current.description),
+        ),
+        None => (None, None),
+    }
+}
+
+/// Returns `None` when the editor fails or the user aborts.
+fn editor_prompt(fields: &ItemFields) -> Option<ItemFields> {
+    match edit_fields(fields) {
+        Ok(fields) => Some(fields),
+        Err(e) => {
+            println!("{}", e);
+            wait_for_key_press();
+            None
+        }
+    }
+}
+
+fn edit_epic_prompt(epic: &Epic) -> EpicUpdate {
+    println!("----------------------------");
+    println!("Leave a field empty to keep its current value.");
+    EpicUpdate {
+        name: edit_field_prompt("Epic Name", &epic.name),
+        description: edit_field_prompt("Epic Description", &epic.description),
+        priority: priority_prompt(epic.priority),
+        due_date: due_date_prompt(epic.due_date),
+    }
+}
+
+fn edit_story_prompt(story: &Story) -> StoryUpdate {
+    println!("----------------------------");
+    println!("Leave a field empty to keep its current value.");
+    StoryUpdate {
+        name: edit_field_prompt("Story Name", &story.name),
+        description: edit_field_prompt("Story Description", &story.description),
+        priority: priority_prompt(story.priority),
+        points: points_prompt(story.points),
+        due_date: due_date_prompt(story.due_date),
+    }
+}
+
+fn edit_field_prompt(label: &str, current: &str) -> Option<String> {
+    println!("{} [{}]:", label, current);
+    let input = get_user_input();
+    if input.is_empty() {
+        None
+    } else {
+        Some(input)
+    }
+}
+
+/// Returns `None` to keep `current`.
+fn priority_prompt(current: Priority) -> Option<Priority> {
+    println!(
+        "Priority (1 - BLOCKER, 2 - CRITICAL, 3 - MAJOR, 4 - MINOR, 5 - TRIVIAL) [{}]:",
+        current
+    );
+    let prompt = get_user_input();
+    match prompt.parse::<u32>().ok() {
+        Some(1) => Some(Priority::Blocker),
+        Some(2) => Some(Priority::Critical),
+        Some(3) => Some(Priority::Major),
+        Some(4) => Some(Priority::Minor),
+        Some(5) => Some(Priority::Trivial),
+        _ => None,
+    }
+}
+
+/// Returns `None` to keep `current`, `Some(None)` to clear it.
+fn points_prompt(current: Option<u32>) -> Option<Option<u32>> {
+    println!(
+        "Story Points ('-' to clear) [{}]:",
+        current.map_or(String::new(), |points| points.to_string())
+    );
+    let prompt = get_user_input();
+    match prompt.as_str() {
+        "-" => Some(None),
+        _ => prompt.parse::<u32>().ok().map(Some),
+    }
+}
+
+/// Returns `None` to keep `current`, `Some(None)` to clear it.
+fn due_date_prompt(current: Option<NaiveDate>) -> Option<Option<NaiveDate>> {
+    println!(
+        "Due Date (YYYY-MM-DD, '-' to clear) [{}]:",
+        current.map_or(String::new(), |date| date.to_string())
+    );
+    let prompt = get_user_input();
+    match prompt.as_str() {
+        "" => None,
+        "-" => Some(None),
+        _ => match prompt.parse::<NaiveDate>() {
+            Ok(date) => Some(Some(date)),
+            Err(_) => {
+                println!("Not a date, keeping the current one.");
+                wait_for_key_press();
+                None
+            }
+        },
+    }
 }
 
 fn delete_epic_prompt() -> bool {
     println!("----------------------------");
-    println!("Are you sure you want to delete this epic? All stories in this epic will also be deleted [Y/n]:");
+    println!("Are you sure you want to delete this epic? All stories in this epic will also be moved to the trash [Y/n]:");
     let name = get_user_input();
     if name.is_empty() {
         true
@@ -75,16 +312,209 @@ fn delete_story_prompt() -> bool {
     }
 }
 
-fn update_status_prompt() -> Option<Status> {
+fn delete_subtask_prompt() -> bool {
+    println!("----------------------------");
+    println!("Are you sure you want to delete this sub-task? [Y/n]:");
+    let prompt = get_user_input();
+    if prompt.is_empty() {
+        true
+    } else {
+        match prompt.as_str() {
+            "Y" => true,
+            "y" => true,
+            "N" => false,
+            "n" => false,
+            _ => false,
+        }
+    }
+}
+
+/// Lists the epics other than `epic_id` and returns the chosen one.
+fn move_story_prompt(db_state: &DBState, epic_id: u32) -> Option<u32> {
+    println!("----------------------------");
+    let mut targets = db_state
+        .epics
+        .iter()
+        .filter(|(id, _)| **id != epic_id)
+        .collect::<Vec<_>>();
+    if targets.is_empty() {
+        println!("There is no other epic to move this story to.");
+        wait_for_key_press();
+        return None;
+    }
+    targets.sort_by_key(|(id, _)| **id);
+
+    for (id, epic) in targets {
+        println!("{} - {}", id, epic.name);
+    }
+    println!("Move story to epic (empty to cancel):");
+    let prompt = get_user_input();
+    prompt
+        .parse::<u32>()
+        .ok()
+        .filter(|id| *id != epic_id && db_state.epics.contains_key(id))
+}
+
+fn assign_prompt(db_state: &DBState) -> Option<Option<u32>> {
     println!("----------------------------");
-    println!("New Status (1 - OPEN, 2 - IN-PROGRESS, 3 - RESOLVED, 4 - CLOSED):");
+    let mut users = db_state.users.iter().collect::<Vec<_>>();
+    users.sort_by_key(|(id, _)| **id);
+    for (id, user) in users {
+        println!("{} - {} ({})", id, user.username, user.name);
+    }
+    println!("Assign to user id or username ('-' to unassign, empty to cancel):");
     let prompt = get_user_input();
-    let pars = prompt.parse::<u32>().ok();
-    match pars {
-        Some(1) => Some(Status::Open),
-        Some(2) => Some(Status::InProgress),
-        Some(3) => Some(Status::Resolved),
-        Some(4) => Some(Status::Closed),
+    match prompt.as_str() {
+        "" => None,
+        "-" => Some(None),
+        _ => {
+            let user_id = match prompt.parse::<u32>() {
+                Ok(id) => Some(id).filter(|id| db_state.users.contains_key(id)),
+                Err(_) => db_state.find_user(&prompt),
+            };
+            if user_id.is_none() {
+                println!("No such user.");
+                wait_for_key_press();
+            }
+            user_id.map(Some)
+        }
+    }
+}
+
+fn edit_labels_prompt(labels: &BTreeSet<String>) -> LabelUpdate {
+    println!("----------------------------");
+    println!("Labels [{}]:", join_labels(labels));
+    println!("Add with +label, remove with -label (empty to cancel):");
+    let update = LabelUpdate::parse(&get_user_input());
+    if !update.is_valid() {
+        println!("Labels cannot be empty or contain spaces or commas.");
+        wait_for_key_press();
+        return LabelUpdate::default();
+    }
+    update
+}
+
+fn new_comment_prompt() -> Option<String> {
+    println!("----------------------------");
+    println!("Comment (empty to cancel):");
+    Some(get_user_input()).filter(|body| !body.is_empty())
+}
+
+fn link_prompt(db_state: &DBState, story_id: u32) -> Option<Link> {
+    println!("----------------------------");
+    println!("Link Type (1 - blocks, 2 - is blocked by, 3 - relates to, 4 - duplicates):");
+    let choice = get_user_input();
+    println!("Other Story ID (empty to cancel):");
+    let other = get_user_input()
+        .parse::<u32>()
+        .ok()
+        .filter(|id| *id != story_id && db_state.stories.contains_key(id))?;
+    match choice.as_str() {
+        "1" => Some(Link::new(story_id, LinkKind::Blocks, other)),
+        "2" => Some(Link::new(other, LinkKind::Blocks, story_id)),
+        "3" => Some(Link::new(story_id, LinkKind::RelatesTo, other)),
+        "4" => Some(Link::new(story_id, LinkKind::Duplicates, other)),
         _ => None,
     }
 }
+
+fn unlink_prompt(db_state: &DBState, story_id: u32) -> Option<u32> {
+    println!("----------------------------");
+    let links = db_state.links_of(story_id);
+    if links.is_empty() {
+        println!("This story has no links.");
+        wait_for_key_press();
+        return None;
+    }
+    for (id, link) in &links {
+        println!("{} - {}", id, link.describe(story_id));
+    }
+    println!("Remove link id (empty to cancel):");
+    let prompt = get_user_input();
+    prompt
+        .parse::<u32>()
+        .ok()
+        .filter(|id| links.iter().any(|(link_id, _)| link_id == id))
+}
+
+fn restore_prompt(db_state: &DBState) -> Option<u32> {
+    println!("----------------------------");
+    println!("Restore epic or story id (empty to cancel):");
+    let prompt = get_user_input();
+    prompt
+        .parse::<u32>()
+        .ok()
+        .filter(|id| db_state.trash.contains_key(id))
+}
+
+fn update_status_prompt(next_states: &[Status]) -> Option<Status> {
+    println!("----------------------------");
+    if next_states.is_empty() {
+        println!("The workflow allows no status change from here.");
+        wait_for_key_press();
+        return None;
+    }
+    let choices = next_states
+        .iter()
+        .enumerate()
+        .map(|(i, status)| format!("{} - {}", i + 1, status))
+        .collect::<Vec<_>>();
+    println!("New Status ({}):", choices.join(", "));
+    let prompt = get_user_input();
+    prompt
+        .parse::<usize>()
+        .ok()
+        .and_then(|choice| next_states.get(choice.checked_sub(1)?))
+        .cloned()
+}
+
+/// Asked on first run when no database exists. Returns `None` to quit, or
+/// whether to seed the new database with a sample project.
+pub fn init_database_prompt(file_path: &str) -> Option<bool> {
+    println!("----------------------------");
+    println!("No Jira database found at {}. Create it? [Y/n]:", file_path);
+    let prompt = get_user_input();
+    if !(prompt.is_empty() || prompt == "Y" || prompt == "y") {
+        return None;
+    }
+
+    println!("Add a sample project? [y/N]:");
+    let prompt = get_user_input();
+    Some(prompt == "Y" || prompt == "y")
+}
+
+fn close_stories_prompt() -> bool {
+    println!("----------------------------");
+    println!("Some stories of this epic are not done. Move them to the same status? [Y/n]:");
+    let prompt = get_user_input();
+    if prompt.is_empty() {
+        true
+    } else {
+        match prompt.as_str() {
+            "Y" => true,
+            "y" => true,
+            "N" => false,
+            "n" => false,
+            _ => false,
+        }
+    }
+}
+
+fn reload_and_retry_prompt() -> bool {
+    println!("----------------------------");
+    println!(
+        "This item was changed by another session since it was shown. Reload and retry? [Y/n]:"
+    );
+    let prompt = get_user_input();
+    if prompt.is_empty() {
+        true
+    } else {
+        match prompt.as_str() {
+            "Y" => true,
+            "y" => true,
+            "N" => false,
+            "n" => false,
+            _ => false,
+        }
+    }
+}